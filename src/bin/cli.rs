@@ -3,9 +3,10 @@
 
 use io::Write;
 use std::io;
+use std::time::SystemTime;
 
 use articy::types::{self, File, Id, Model, Pin, Type};
-use articy::{Interpreter, Outcome};
+use articy::{ErrorPolicy, Interpreter, Outcome};
 
 use evalexpr::{
     eval_boolean_with_context, eval_boolean_with_context_mut, eval_with_context_mut,
@@ -13,13 +14,43 @@ use evalexpr::{
 };
 
 fn main() {
-    let json = std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+    // Standalone usage: command-line-runner search <file> <query>
+    let args = std::env::args().collect::<Vec<String>>();
+    if let [_, command, path, query @ ..] = args.as_slice() {
+        if command == "search" {
+            match load_file(path) {
+                Ok(file) => print_search_hits(&file, &query.join(" ")),
+                Err(error) => println!("could not load {path}: {error:?}"),
+            }
+
+            return;
+        }
+    }
 
-    let articy_file: File = serde_json::from_str(&json).expect("to be able to parse articy data");
+    // Interactive usage: command-line-runner [--watch] [file]
+    let path = args
+        .iter()
+        .skip(1)
+        .find(|arg| !arg.starts_with("--"))
+        .map(String::as_str)
+        .unwrap_or("./craftcraft.json");
+    let mut watch = args
+        .iter()
+        .any(|arg| arg == "--watch")
+        .then(|| Watch::new(path));
+
+    let articy_file = match load_file(path) {
+        Ok(file) => file,
+        Err(error) => {
+            println!("could not load {path}: {error:?}");
+            return;
+        }
+    };
 
     let start_id = Id("0x0100000100000529".into());
 
     let mut interpreter = Interpreter::new(articy_file.into());
+    interpreter.error_policy = ErrorPolicy::Log;
     // let _ = interpreter.set_state("quality.groundskeeper_dagger", articy::StateValue::Int(2));
     let _ = interpreter.set_state(
         "item_selection.daywatch_weapon_choice",
@@ -30,7 +61,7 @@ fn main() {
 
     println!("Starting with state:\n{:#?}\n---\n", interpreter.state);
     // DAY 1
-    interpreter.start(start_id).unwrap();
+    interpreter.start(start_id.clone()).unwrap();
 
     let stdin = io::stdin();
     let mut stdout = io::stdout();
@@ -54,11 +85,25 @@ fn main() {
         let mut buffer = String::new();
         stdin.read_line(&mut buffer).unwrap();
 
-        let buffer = buffer.to_lowercase();
+        if watch.as_mut().is_some_and(Watch::changed) {
+            // NOTE: Articy may still be writing the export, the old file is kept until it parses
+            match load_file(path) {
+                Ok(file) => {
+                    if interpreter.reload(file.into()) {
+                        println!("Reloaded {path}");
+                    } else {
+                        println!("Reloaded {path}, the current node is gone so starting over");
+                        interpreter.start(start_id.clone()).unwrap();
+                    }
+                }
+                Err(error) => println!("could not reload {path}, keeping the old file: {error:?}"),
+            }
+        }
+
         let mut buffer = buffer.trim().split(' ');
-        let command = buffer.next().unwrap();
+        let command = buffer.next().unwrap().to_lowercase();
 
-        match command {
+        match command.as_str() {
             "view" | "v" => {
                 println!("Current node:\n{:#?}", interpreter.get_current_model())
             }
@@ -75,8 +120,7 @@ fn main() {
                 let id = match interpreter
                     .get_available_connections_at_cursor()
                     .unwrap_or_default()
-                    .iter()
-                    .nth(choice)
+                    .get(choice)
                 {
                     Some(model) => model.id(),
                     None => {
@@ -87,51 +131,146 @@ fn main() {
 
                 interpreter.choose(id).unwrap();
             }
+            "list" | "l" => print_startable_nodes(&interpreter.file),
+            "search" | "s" => {
+                let query = buffer.collect::<Vec<&str>>().join(" ");
+                print_search_hits(&interpreter.file, &query);
+            }
+            "goto" | "g" => {
+                let Some(target) = buffer.next() else {
+                    println!("usage: goto <id-or-technical-name> [--honor]");
+                    continue;
+                };
+                let honor_conditions = buffer.any(|flag| flag == "--honor");
+
+                let Some(id) = interpreter.file.find_model(target).map(|model| model.id()) else {
+                    println!("could not find a node with the id or technical name {target:?}");
+                    continue;
+                };
+
+                if !honor_conditions {
+                    println!("warning: jumping without checking entry conditions, use --honor to check them");
+                }
+
+                if let Err(error) = interpreter.jump_to(id, honor_conditions) {
+                    println!("could not jump there: {error:?}");
+                }
+            }
             "" => match interpreter.advance().unwrap() {
                 Outcome::Advanced(_) => {}
-                Outcome::WaitingForChoice(_) => display_choices(&interpreter),
-                Outcome::Stopped | Outcome::EndOfDialogue => break 'game,
+                Outcome::WaitingForChoice(_) | Outcome::ReturnedToHub { .. } => {
+                    display_choices(&interpreter)
+                }
+                Outcome::Stopped | Outcome::EndOfDialogue { .. } => break 'game,
             },
             _ => {}
         }
     }
 }
 
+fn print_startable_nodes(file: &File) {
+    for node in file.list_startable_nodes() {
+        let mut path = node.path.join(" / ");
+        if !path.is_empty() {
+            path.push_str(" / ");
+        }
+
+        println!(
+            "{} {path}{} [{}]",
+            node.id.as_str(),
+            node.display_name,
+            node.technical_name
+        );
+    }
+}
+
+fn load_file(path: &str) -> Result<File, types::Error> {
+    File::from_path(path)
+}
+
+/// Notices re-exports by polling the modification time of the file
+struct Watch {
+    path: String,
+    modified: Option<SystemTime>,
+}
+
+impl Watch {
+    fn new(path: &str) -> Self {
+        Watch {
+            path: path.to_owned(),
+            modified: Self::modified(path),
+        }
+    }
+
+    fn modified(path: &str) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    fn changed(&mut self) -> bool {
+        let modified = Self::modified(&self.path);
+        let changed = modified.is_some() && modified != self.modified;
+        self.modified = modified;
+
+        changed
+    }
+}
+
+fn print_search_hits(file: &File, query: &str) {
+    let hits = file.search(query);
+
+    println!("\n{} matches for {query:?}:\n---", hits.len());
+    for hit in hits {
+        let speaker = match hit.model {
+            Model::DialogueFragment { speaker, .. } => file
+                .get_model(speaker)
+                .and_then(|speaker| speaker.display_name())
+                .unwrap_or_default(),
+            _ => String::new(),
+        };
+
+        println!(
+            "{id} (#{short_id}) {kind} {speaker} [{field}] {snippet}",
+            id = hit.model.id().as_str(),
+            short_id = hit
+                .model
+                .short_id()
+                .map(|short_id| short_id.to_string())
+                .unwrap_or_default(),
+            kind = hit.model.type_name(),
+            field = hit.field.name(),
+            snippet = hit.snippet,
+        );
+    }
+
+    println!("\n");
+}
+
 fn display_choices(interpreter: &Interpreter) {
-    let models = interpreter.get_available_connections_at_cursor().unwrap();
+    let group = interpreter.get_choice_group().unwrap();
 
-    let mut choice = 0;
-    println!("\nAvailable choices:\n---");
-    for model in models {
+    match group.hub {
+        Some(hub) if !hub.display_name.is_empty() => {
+            println!("\n{} ({}):\n---", hub.display_name, hub.color.to_hex())
+        }
+        _ => println!("\nAvailable choices:\n---"),
+    }
+    for (choice, option) in group.options().into_iter().enumerate() {
         println!(
-            "({choice}): {node_name} {condition}",
-            condition = match model
-                .input_pins()
-                .expect("Model to have input pins")
-                .first() // NOTE: Assuming that the first input pin is the one we care about
-                .unwrap()
-                .text
-                .as_str()
-            {
-                "" => "".to_string(),
-                expression => {
-                    let outcome = match eval_boolean_with_context(expression, &interpreter.state) {
-                        Ok(outcome) => outcome,
-                        Err(_) => false,
-                    };
-                    format!("({expression} ({outcome}))")
+            "({choice}): {label} {condition}",
+            condition = match interpreter.file.get_pin(&option.connection.target_pin) {
+                Some(pin) if !pin.text.is_empty() => {
+                    let outcome = interpreter.evaluate_pin(pin).unwrap_or_default();
+                    format!("({} ({outcome}))", pin.text)
                 }
+                _ => "".to_string(),
             },
-            node_name = match model {
-                Model::DialogueFragment { text, .. } => text.to_owned(),
-                _ => match model.display_name() {
-                    Some(display_name) => display_name,
-                    _ => "Unknown name".to_owned(),
-                },
+            label = match interpreter.choice_label(&option).as_str() {
+                "" => "Unknown name".to_owned(),
+                label => label.to_owned(),
             }
         );
-
-        choice += 1;
     }
 
     println!("\n");
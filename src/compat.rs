@@ -0,0 +1,97 @@
+use serde_json::{Map, Value};
+
+use crate::types::File;
+
+/// The tool an export was made with, detected from `Settings.ExportVersion`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// articy:draft 3.x, export version 1.x
+    Draft3,
+    /// articy:draft X, export version 2.x and newer
+    DraftX,
+}
+
+impl ExportFormat {
+    pub fn from_version(version: &str) -> Self {
+        let major = version
+            .split('.')
+            .next()
+            .and_then(|major| major.trim().parse::<u32>().ok())
+            .unwrap_or(1);
+
+        if major >= 2 {
+            ExportFormat::DraftX
+        } else {
+            ExportFormat::Draft3
+        }
+    }
+
+    /// Detects the format of a snake cased export that hasn't been deserialized yet
+    pub(crate) fn detect(value: &Value) -> Self {
+        value
+            .pointer("/settings/export_version")
+            .and_then(Value::as_str)
+            .map(ExportFormat::from_version)
+            .unwrap_or(ExportFormat::Draft3)
+    }
+}
+
+impl File {
+    pub fn export_format(&self) -> ExportFormat {
+        ExportFormat::from_version(&self.settings.export_version)
+    }
+}
+
+/// Top level blocks renamed in articy:draft X, as (X name, 3.x name)
+const RENAMED_BLOCKS: &[(&str, &str)] = &[("type_definitions", "object_definitions")];
+
+/// Model properties renamed in articy:draft X, as (X name, 3.x name)
+const RENAMED_PROPERTIES: &[(&str, &str)] = &[("z_order", "z_index")];
+
+/// Rewrites a snake cased export into the 3.x shape the types are modelled after,
+/// properties that were only added in X are left alone and ignored during deserialization.
+pub(crate) fn normalize(mut value: Value) -> Value {
+    if ExportFormat::detect(&value) == ExportFormat::Draft3 {
+        return value;
+    }
+
+    let Some(root) = value.as_object_mut() else {
+        return value;
+    };
+
+    rename_keys(root, RENAMED_BLOCKS);
+
+    let packages = root
+        .get_mut("packages")
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten();
+
+    for package in packages {
+        let models = package
+            .get_mut("models")
+            .and_then(Value::as_array_mut)
+            .into_iter()
+            .flatten();
+
+        for properties in models.filter_map(|model| model.get_mut("properties")) {
+            if let Some(properties) = properties.as_object_mut() {
+                rename_keys(properties, RENAMED_PROPERTIES);
+            }
+        }
+    }
+
+    value
+}
+
+fn rename_keys(map: &mut Map<String, Value>, renames: &[(&str, &str)]) {
+    for (from, to) in renames {
+        if map.contains_key(*to) {
+            continue;
+        }
+
+        if let Some(value) = map.remove(*from) {
+            map.insert(to.to_string(), value);
+        }
+    }
+}
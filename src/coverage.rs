@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::flow::pins;
+use crate::types::{File, Id, Model, Type};
+
+/// The nodes and connections that were traversed, by one playthrough or merged over many.
+/// Serializable so playtest sessions can be saved and aggregated afterwards.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    pub nodes: HashSet<Id>,
+    /// (node the connection leaves from, input pin it leads to)
+    pub connections: HashSet<(Id, Id)>,
+}
+
+impl Coverage {
+    pub fn record_node(&mut self, id: &Id) {
+        self.nodes.insert(id.clone());
+    }
+
+    pub fn record_connection(&mut self, from: &Id, target_pin: &Id) {
+        self.connections.insert((from.clone(), target_pin.clone()));
+    }
+
+    pub fn merge(&mut self, other: &Coverage) {
+        self.nodes.extend(other.nodes.iter().cloned());
+        self.connections.extend(other.connections.iter().cloned());
+    }
+
+    /// Coverage of every Dialogue in the file
+    pub fn report(&self, file: &File) -> Vec<DialogueCoverage> {
+        file.get_models_of_type(Type::Dialogue)
+            .into_iter()
+            .map(|dialogue| self.dialogue_coverage(file, &dialogue.id()))
+            .collect()
+    }
+
+    /// Coverage of the nodes directly inside of a Dialogue and the connections leaving them
+    pub fn dialogue_coverage(&self, file: &File, dialogue_id: &Id) -> DialogueCoverage {
+        let mut coverage = DialogueCoverage {
+            dialogue: dialogue_id.clone(),
+            nodes: 0,
+            nodes_seen: 0,
+            connections: 0,
+            connections_seen: 0,
+            never_seen_lines: vec![],
+        };
+
+        for model in file
+            .get_models()
+            .into_iter()
+            .filter(|model| &model.parent() == dialogue_id)
+        {
+            let id = model.id();
+
+            coverage.nodes += 1;
+            if self.nodes.contains(&id) {
+                coverage.nodes_seen += 1;
+            } else if let Model::DialogueFragment { .. } = model {
+                coverage.never_seen_lines.push(id.clone());
+            }
+
+            for connection in pins(model, "output_pins")
+                .iter()
+                .flat_map(|pin| &pin.connections)
+            {
+                coverage.connections += 1;
+                if self
+                    .connections
+                    .contains(&(id.clone(), connection.target_pin.clone()))
+                {
+                    coverage.connections_seen += 1;
+                }
+            }
+        }
+
+        coverage
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogueCoverage {
+    pub dialogue: Id,
+    pub nodes: usize,
+    pub nodes_seen: usize,
+    pub connections: usize,
+    pub connections_seen: usize,
+    /// DialogueFragments that were never reached
+    pub never_seen_lines: Vec<Id>,
+}
+
+impl DialogueCoverage {
+    /// Percentage of the nodes that were reached, 100 for empty dialogues
+    pub fn node_percentage(&self) -> f32 {
+        percentage(self.nodes_seen, self.nodes)
+    }
+
+    /// Percentage of the connections that were followed, 100 for dialogues without connections
+    pub fn connection_percentage(&self) -> f32 {
+        percentage(self.connections_seen, self.connections)
+    }
+}
+
+fn percentage(seen: usize, total: usize) -> f32 {
+    if total == 0 {
+        100.0
+    } else {
+        seen as f32 / total as f32 * 100.0
+    }
+}
@@ -0,0 +1,197 @@
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::types::{File, Hierarchy, Id, Model};
+
+impl File {
+    /// Creates a standalone File containing only the given Dialogue/FlowFragment, everything below it in the hierarchy
+    /// and the models it references (speakers, template references, ...) together with their parents.
+    /// Connections leading out of the extracted models are dropped, global variables are kept per namespace if used.
+    pub fn extract_subtree(&self, id: &Id) -> File {
+        let mut included = HashSet::new();
+        let mut pending = vec![];
+
+        if let Some(node) = self.hierarchy.find(id) {
+            collect_hierarchy_ids(node, &mut pending);
+        } else {
+            pending.push(id.clone());
+        }
+
+        while let Some(id) = pending.pop() {
+            let Some(model) = self.get_model(&id) else {
+                continue;
+            };
+
+            if !included.insert(id) {
+                continue;
+            }
+
+            pending.extend(referenced_ids(model).filter(|id| self.get_model(id).is_some()));
+        }
+
+        // Parents are kept (without following their references) so hierarchy paths stay intact
+        for id in included.clone() {
+            let mut parent = self.get_model(&id).map(|model| model.parent());
+
+            while let Some(model) = parent.and_then(|parent| self.get_model(&parent)) {
+                if !included.insert(model.id()) {
+                    break;
+                }
+
+                parent = Some(model.parent());
+            }
+        }
+
+        let mut file = self.clone();
+        file.model_index = Default::default();
+        file.short_id_index = Default::default();
+
+        for package in &mut file.packages {
+            package
+                .models
+                .retain(|model| included.contains(&model.id()));
+
+            for model in &mut package.models {
+                retain_connections(model, &included);
+            }
+        }
+
+        file.custom_models.0.retain(|id, _| included.contains(id));
+
+        let expressions = file
+            .get_models()
+            .into_iter()
+            .flat_map(|model| {
+                let mut expressions = vec![];
+
+                if let Some(Value::String(expression)) = model.get_property("expression") {
+                    expressions.push(expression);
+                }
+
+                for pins in [model.input_pins(), model.output_pins()]
+                    .into_iter()
+                    .flatten()
+                {
+                    expressions.extend(pins.iter().map(|pin| pin.text.clone()));
+                }
+
+                expressions
+            })
+            .collect::<Vec<String>>();
+
+        file.global_variables.retain(|global_variable| {
+            let prefix = format!("{}.", global_variable.namespace);
+
+            expressions
+                .iter()
+                .any(|expression| expression.contains(&prefix))
+        });
+
+        if let Some(children) = &mut file.hierarchy.children {
+            retain_hierarchy(children, &included);
+        }
+
+        file
+    }
+}
+
+fn collect_hierarchy_ids(node: &Hierarchy, ids: &mut Vec<Id>) {
+    ids.push(node.id.clone());
+
+    for child in node.children.iter().flatten() {
+        collect_hierarchy_ids(child, ids);
+    }
+}
+
+/// All ids mentioned in the model's properties, ignoring its own id, parent and pins
+fn referenced_ids(model: &Model) -> impl Iterator<Item = Id> {
+    let mut properties = match model {
+        Model::Custom(_, properties) => properties.clone(),
+        _ => serde_json::to_value(model)
+            .ok()
+            .and_then(|value| value.get("properties").cloned())
+            .unwrap_or_default(),
+    };
+
+    if let Some(properties) = properties.as_object_mut() {
+        for key in ["id", "parent", "input_pins", "output_pins"] {
+            properties.remove(key);
+        }
+    }
+
+    let mut strings = vec![];
+    collect_strings(&properties, &mut strings);
+
+    strings.into_iter().map(|string| Id(string.into()))
+}
+
+fn collect_strings(value: &Value, strings: &mut Vec<String>) {
+    match value {
+        Value::String(string) => strings.push(string.clone()),
+        Value::Array(array) => array
+            .iter()
+            .for_each(|value| collect_strings(value, strings)),
+        Value::Object(object) => object
+            .values()
+            .for_each(|value| collect_strings(value, strings)),
+        _ => {}
+    }
+}
+
+fn retain_connections(model: &mut Model, included: &HashSet<Id>) {
+    let pins = match model {
+        Model::FlowFragment {
+            input_pins,
+            output_pins,
+            ..
+        }
+        | Model::DialogueFragment {
+            input_pins,
+            output_pins,
+            ..
+        }
+        | Model::Hub {
+            input_pins,
+            output_pins,
+            ..
+        }
+        | Model::Dialogue {
+            input_pins,
+            output_pins,
+            ..
+        }
+        | Model::Condition {
+            input_pins,
+            output_pins,
+            ..
+        }
+        | Model::Instruction {
+            input_pins,
+            output_pins,
+            ..
+        } => input_pins.iter_mut().chain(output_pins.iter_mut()),
+        _ => return,
+    };
+
+    for pin in pins {
+        pin.connections
+            .retain(|connection| included.contains(&connection.target));
+    }
+}
+
+fn retain_hierarchy(children: &mut Vec<Hierarchy>, included: &HashSet<Id>) {
+    for child in children.iter_mut() {
+        if let Some(grandchildren) = &mut child.children {
+            retain_hierarchy(grandchildren, included);
+        }
+    }
+
+    children.retain(|child| {
+        included.contains(&child.id)
+            || child
+                .children
+                .as_ref()
+                .is_some_and(|children| !children.is_empty())
+    });
+}
@@ -0,0 +1,281 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+#[cfg(feature = "interpreter")]
+use evalexpr::{eval_boolean_with_context, HashMapContext};
+
+use crate::types::{File, Id, Model, Pin};
+
+impl File {
+    /// Walks the connection graph depth-first starting at (and including) the given node,
+    /// every node is only visited once so looping flows are safe to iterate.
+    pub fn iter_flow_from(&self, id: &Id) -> FlowIter<'_> {
+        FlowIter {
+            file: self,
+            stack: vec![(id.clone(), None, 0)],
+            walked: HashSet::new(),
+            yielded: HashSet::new(),
+            max_depth: None,
+            filter: None,
+        }
+    }
+
+    /// The terminal nodes of a Dialogue, i.e the nodes inside of it connecting to one of its own output pins,
+    /// a node leaving through several output pins is listed once per pin.
+    pub fn get_endings(&self, dialogue_id: &Id) -> Vec<Ending<'_>> {
+        let Some(dialogue) = self.get_model(dialogue_id) else {
+            return vec![];
+        };
+        let exit_pins = pins(dialogue, "output_pins");
+
+        let mut endings = vec![];
+        for model in &self.get_default_package().models {
+            for connection in pins(model, "output_pins")
+                .iter()
+                .flat_map(|pin| &pin.connections)
+            {
+                let is_exit = &connection.target == dialogue_id
+                    && exit_pins.iter().any(|pin| pin.id == connection.target_pin);
+
+                if is_exit {
+                    endings.push(Ending {
+                        node: model,
+                        exit_pin: connection.target_pin.clone(),
+                    });
+                }
+            }
+        }
+
+        endings
+    }
+
+    /// Finds the shortest chain of nodes leading from one node to another (both included)
+    pub fn find_path(&self, from: &Id, to: &Id) -> Option<Vec<Id>> {
+        self.shortest_path(from, to, None)
+    }
+
+    /// Same as `find_path`, but pin conditions and Condition nodes are evaluated against the given state
+    /// so only paths the player could actually take are considered. Instructions along the path are not applied.
+    #[cfg(feature = "interpreter")]
+    pub fn find_path_with_state(
+        &self,
+        from: &Id,
+        to: &Id,
+        state: &HashMapContext,
+    ) -> Option<Vec<Id>> {
+        self.shortest_path(from, to, Some(state))
+    }
+
+    fn shortest_path(&self, from: &Id, to: &Id, state: Option<&dyn Conditions>) -> Option<Vec<Id>> {
+        let start = (from.clone(), None);
+        let mut previous: HashMap<(Id, Option<Id>), (Id, Option<Id>)> = HashMap::new();
+        let mut seen = HashSet::from([start.clone()]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some((id, entered_through)) = queue.pop_front() {
+            if &id == to {
+                let mut path = vec![id.clone()];
+                let mut step = (id, entered_through);
+
+                while let Some(previous_step) = previous.get(&step) {
+                    path.push(previous_step.0.clone());
+                    step = previous_step.clone();
+                }
+
+                path.reverse();
+
+                return Some(path);
+            }
+
+            let Some(model) = self.get_model(&id) else {
+                continue;
+            };
+
+            for pin in followed_pins(model, entered_through.as_ref(), state) {
+                for connection in &pin.connections {
+                    let next = (
+                        connection.target.clone(),
+                        Some(connection.target_pin.clone()),
+                    );
+
+                    if let Some(state) = state {
+                        if !self.is_pin_open(&next.0, &connection.target_pin, state) {
+                            continue;
+                        }
+                    }
+
+                    if seen.insert(next.clone()) {
+                        previous.insert(next.clone(), (id.clone(), entered_through.clone()));
+                        queue.push_back(next);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+
+    fn is_pin_open(&self, target: &Id, target_pin: &Id, state: &dyn Conditions) -> bool {
+        let Some(model) = self.get_model(target) else {
+            return false;
+        };
+
+        match pins(model, "input_pins")
+            .iter()
+            .find(|pin| &pin.id == target_pin)
+        {
+            Some(pin) if !pin.text.is_empty() => state.is_true(&pin.text),
+            _ => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Ending<'a> {
+    pub node: &'a Model,
+    /// The output pin of the Dialogue the flow exits through
+    pub exit_pin: Id,
+}
+
+/// Evaluates conditions while walking the flow, expressions that fail to evaluate count as false
+trait Conditions {
+    fn is_true(&self, expression: &str) -> bool;
+}
+
+#[cfg(feature = "interpreter")]
+impl Conditions for HashMapContext {
+    fn is_true(&self, expression: &str) -> bool {
+        eval_boolean_with_context(expression, self).unwrap_or_default()
+    }
+}
+
+pub struct FlowIter<'a> {
+    file: &'a File,
+    // (node, pin the node was entered through, depth)
+    stack: Vec<(Id, Option<Id>, usize)>,
+    walked: HashSet<(Id, Option<Id>)>,
+    yielded: HashSet<Id>,
+    max_depth: Option<usize>,
+    filter: Option<Box<ModelFilter<'a>>>,
+}
+
+type ModelFilter<'a> = dyn Fn(&Model) -> bool + 'a;
+
+impl<'a> FlowIter<'a> {
+    /// Stops walking past nodes that are `max_depth` connections away from the start
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Nodes failing the filter are neither yielded nor walked through
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Model) -> bool + 'a,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+}
+
+impl<'a> Iterator for FlowIter<'a> {
+    type Item = &'a Model;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((id, entered_through, depth)) = self.stack.pop() {
+            if !self.walked.insert((id.clone(), entered_through.clone())) {
+                continue;
+            }
+
+            let Some(model) = self.file.get_model(&id) else {
+                continue;
+            };
+
+            if let Some(filter) = &self.filter {
+                if !filter(model) {
+                    continue;
+                }
+            }
+
+            if self.max_depth.is_none_or(|max_depth| depth < max_depth) {
+                let next = next_connections(model, entered_through.as_ref());
+
+                self.stack.extend(
+                    next.into_iter()
+                        .rev()
+                        .map(|(target, target_pin)| (target, Some(target_pin), depth + 1)),
+                );
+            }
+
+            if self.yielded.insert(id) {
+                return Some(model);
+            }
+        }
+
+        None
+    }
+}
+
+pub(crate) fn pins(model: &Model, key: &str) -> Vec<Pin> {
+    let pins = match key {
+        "input_pins" => model.input_pins(),
+        _ => model.output_pins(),
+    };
+
+    match (pins, model) {
+        (Some(pins), _) => pins.clone(),
+        (None, Model::Custom(_, value)) => value
+            .get(key)
+            .and_then(|pins| serde_json::from_value(pins.clone()).ok())
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+/// Where the flow continues after `model`, as (target, target pin) pairs.
+/// Containers (Dialogues, FlowFragments) are walked into through their input pin and left through their output pins.
+pub(crate) fn next_connections(model: &Model, entered_through: Option<&Id>) -> Vec<(Id, Id)> {
+    followed_pins(model, entered_through, None)
+        .iter()
+        .flat_map(|pin| &pin.connections)
+        .map(|connection| (connection.target.clone(), connection.target_pin.clone()))
+        .collect()
+}
+
+/// The pins the flow leaves `model` through, when a state is given Conditions only follow the pin of their outcome
+fn followed_pins(
+    model: &Model,
+    entered_through: Option<&Id>,
+    state: Option<&dyn Conditions>,
+) -> Vec<Pin> {
+    let output_pins = pins(model, "output_pins");
+    let input_pins = pins(model, "input_pins");
+
+    if let Some(pin) =
+        entered_through.and_then(|pin_id| output_pins.iter().find(|pin| &pin.id == pin_id))
+    {
+        return vec![pin.clone()];
+    }
+
+    let entering = input_pins
+        .into_iter()
+        .filter(|pin| entered_through.is_none_or(|pin_id| &pin.id == pin_id))
+        .filter(|pin| !pin.connections.is_empty())
+        .collect::<Vec<Pin>>();
+
+    if !entering.is_empty() {
+        return entering;
+    }
+
+    match (model, state) {
+        (Model::Condition { expression, .. }, Some(state)) => {
+            let pin = if state.is_true(expression) {
+                output_pins.first()
+            } else {
+                output_pins.last()
+            };
+
+            pin.cloned().into_iter().collect()
+        }
+        _ => output_pins,
+    }
+}
@@ -0,0 +1,1457 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::coverage::Coverage;
+use crate::flow;
+use crate::localization::{Localizer, StringTable, TextField};
+use crate::query::MAX_HIERARCHY_DEPTH;
+use crate::transcript::{Transcript, TranscriptEntryKind};
+use crate::types::{
+    Color, Connection, Error, ExpressionError, ExpressionSource, File, Id, Model, Pin,
+    ResolvedAsset, VariableValue,
+};
+
+pub use evalexpr::Value as StateValue;
+
+/// Interpreter variables by their full name (e.g `quests.stage`), sorted so saves stay stable
+pub type Variables = BTreeMap<String, StateValue>;
+use evalexpr::{
+    eval_boolean_with_context, eval_with_context_mut, Context, ContextWithMutableVariables,
+    EvalexprResult, HashMapContext, IterateVariablesContext,
+};
+
+pub struct Interpreter {
+    pub file: Arc<File>,
+    pub state: HashMapContext,
+    pub visited: Vec<Id>,
+    pub finished: Vec<Id>,
+    pub cursor: Option<Id>,
+    /// The input pin the node at the cursor was entered through, `None` when it was jumped to directly
+    pub entered_pin: Option<Id>,
+
+    custom_handlers: HashMap<String, Box<CustomHandler>>,
+    watchers: HashMap<String, Vec<Box<Watcher>>>,
+    translations: HashMap<(Id, TextField), String>,
+    // (locale, node, field) -> text, see `set_text_override`
+    text_overrides: HashMap<(Option<String>, Id, TextField), String>,
+    localizers: HashMap<String, Box<dyn Localizer>>,
+    locale: Option<String>,
+    metrics: Cell<Metrics>,
+    pub error_policy: ErrorPolicy,
+    expression_errors: RefCell<Vec<ExpressionError>>,
+    pub dead_end_policy: DeadEndPolicy,
+    /// How many Conditions in a row are followed before giving up with `Error::TraversalLoop`
+    pub max_technical_depth: usize,
+    // (hub, option chosen at the hub)
+    hub_stack: Vec<(Id, Id)>,
+    pub budget: Budget,
+    /// Set to `Some` to record which nodes and connections are traversed, e.g during playtests
+    pub coverage: Option<Coverage>,
+    /// Set to `Some` to record the lines, choices and variable changes of the playthrough
+    pub transcript: Option<Transcript>,
+    /// Namespaces of variables local to a dialogue (e.g `conversation` for `conversation.*`), their values are
+    /// remembered when the flow enters a Dialogue and put back once it leaves it
+    pub local_namespaces: Vec<String>,
+    // The dialogue the cursor is in, with the local variables as they were on entering it
+    local_scope: Option<(Id, Variables)>,
+    auto_advance: Option<Box<SpeakerFilter>>,
+    events: RefCell<VecDeque<Event>>,
+    /// How many events `recent_events` keeps, the oldest are dropped first
+    pub event_capacity: usize,
+    // Lines auto advanced through by the current call to `advance`/`choose`
+    auto_advanced: Vec<Id>,
+    // What the latest call to `advance`/`choose`/`jump_to` used up, `None` before the first one.
+    // The public lookups evaluating conditions set it aside while they run so they're never counted.
+    budget_used: Cell<Option<BudgetUsage>>,
+}
+
+/// Limits on the work a single call to `advance`, `choose` or `jump_to` may do before failing
+/// with `Error::BudgetExceeded`, `None` means unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Budget {
+    /// Conditions, pin conditions and instructions evaluated
+    pub max_evaluations: Option<u32>,
+    /// Conditions and Instructions passed through
+    pub max_technical_nodes: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BudgetUsage {
+    evaluations: u32,
+    technical_nodes: u32,
+}
+
+/// What happens when the flow reaches a node without any outgoing connection
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum DeadEndPolicy {
+    /// `advance` returns `Error::NoOutputConnected`
+    #[default]
+    End,
+    /// Goes back to the hub the branch was chosen at, returning `Outcome::ReturnedToHub`
+    ReturnToHub,
+}
+
+/// How the interpreter deals with conditions and instructions that fail to evaluate,
+/// apart from `Fail` a failing condition counts as false and a failing instruction is skipped
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ErrorPolicy {
+    Ignore,
+    /// Prints the error to stderr
+    Log,
+    /// Keeps the errors around until taken with `Interpreter::take_expression_errors`
+    #[default]
+    Collect,
+    /// Returns `Error::Expression` from the call that evaluated the expression
+    Fail,
+}
+
+/// Counters of the work done by the interpreter, see `Interpreter::metrics`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Metrics {
+    pub nodes_advanced: u64,
+    pub conditions_evaluated: u64,
+    /// Conditions (pin conditions included) that evaluated to false
+    pub condition_failures: u64,
+    pub instructions_executed: u64,
+    /// Conditions and instructions that couldn't be evaluated at all
+    pub expression_errors: u64,
+    pub evaluation_time: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub enum Outcome<'a> {
+    Advanced(&'a Model),
+    WaitingForChoice(Vec<&'a Model>),
+    /// A branch dead-ended and `DeadEndPolicy::ReturnToHub` went back to the hub it was chosen at
+    ReturnedToHub {
+        /// The option at the hub the branch started with
+        exhausted: Id,
+        choices: Vec<&'a Model>,
+    },
+    Stopped,
+    EndOfDialogue {
+        dialogue: Id,
+        /// The output pin of the Dialogue the flow left through, `None` if the Dialogue was moved to directly
+        exit_pin: Option<Id>,
+    },
+}
+
+/// Everything needed to show a DialogueFragment, with its texts localized for the current locale
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineView {
+    pub id: Id,
+    /// `None` if the fragment has no speaker or the speaker isn't part of the export
+    pub speaker: Option<Id>,
+    pub speaker_name: Option<String>,
+    /// The preview image of the speaker
+    pub portrait: Option<ResolvedAsset>,
+    pub text: String,
+    pub stage_directions: String,
+    pub menu_text: String,
+}
+
+/// The choices at the cursor together with the Hub they're offered at, see `Interpreter::get_choice_group`
+#[derive(Debug, Clone)]
+pub struct ChoiceGroup<'a> {
+    /// `None` when the choices don't start at a Hub, e.g a DialogueFragment with several connections
+    pub hub: Option<HubHeader<'a>>,
+    pub choices: Vec<&'a Model>,
+    /// The connections leading to each of the choices, in the same order
+    pub connections: Vec<&'a Connection>,
+}
+
+impl<'a> ChoiceGroup<'a> {
+    pub fn options(&self) -> Vec<ChoiceOption<'a>> {
+        self.choices
+            .iter()
+            .zip(&self.connections)
+            .map(|(model, connection)| ChoiceOption { model, connection })
+            .collect()
+    }
+}
+
+/// A single choice together with the connection leading to it
+#[derive(Debug, Clone, Copy)]
+pub struct ChoiceOption<'a> {
+    pub model: &'a Model,
+    pub connection: &'a Connection,
+}
+
+impl ChoiceOption<'_> {
+    /// The fields Articy falls back through when showing a choice: menu text, text, display name
+    pub const LABEL_FALLBACK: [TextField; 3] =
+        [TextField::MenuText, TextField::Text, TextField::DisplayName];
+
+    /// The text to show for the choice following `LABEL_FALLBACK`, empty when all of them are.
+    /// Uses the exported texts, see `Interpreter::choice_label` for the overridden and translated ones.
+    pub fn label(&self) -> String {
+        self.label_with(&Self::LABEL_FALLBACK)
+    }
+
+    /// The first of the given fields that isn't empty
+    pub fn label_with(&self, fallback: &[TextField]) -> String {
+        fallback
+            .iter()
+            .filter_map(|field| self.model.text_field(*field))
+            .find(|text| !text.trim().is_empty())
+            .unwrap_or_default()
+    }
+}
+
+/// The parts of a Hub UIs show above its choices
+#[derive(Debug, Clone, Copy)]
+pub struct HubHeader<'a> {
+    pub id: &'a Id,
+    pub technical_name: &'a str,
+    pub display_name: &'a str,
+    pub color: &'a Color,
+}
+
+type CustomHandler = dyn FnMut(&Model, &mut HandlerContext) -> Handled;
+
+/// Something that happened while traversing, see `Interpreter::recent_events`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub at: SystemTime,
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    NodeEntered(Id),
+    ChoiceMade(Id),
+    ExpressionFailed(ExpressionError),
+    /// An Instruction with several outgoing connections only followed one of them, `None` if none was open
+    BranchesDropped {
+        node: Id,
+        followed: Option<Id>,
+    },
+    /// A snapshot was restored with `ContentMismatch::Warn` on an export it wasn't created with
+    ContentMismatch {
+        expected: u64,
+        found: u64,
+    },
+}
+
+/// Decides by the speaker of a DialogueFragment whether it's advanced through, see `Interpreter::auto_advance_when`
+type SpeakerFilter = dyn Fn(&Id) -> bool;
+
+/// Called with the old (`None` if it wasn't set before) and new value of a watched variable
+type Watcher = dyn FnMut(Option<&StateValue>, &StateValue);
+
+/// What the interpreter should do after a handler registered with `Interpreter::on_custom` ran
+#[derive(Debug, Clone)]
+pub enum Handled {
+    /// Moves the cursor to the given node and keeps advancing from there
+    Continue(Id),
+    /// Leaves the cursor on the custom node and returns `Outcome::Stopped`
+    Stop,
+}
+
+/// Gives handlers access to the loaded file and the interpreter state
+pub struct HandlerContext<'a> {
+    pub file: &'a File,
+    pub state: &'a mut HashMapContext,
+}
+
+impl Interpreter {
+    pub fn new(file: Arc<File>) -> Self {
+        Interpreter {
+            file,
+            state: HashMapContext::new(),
+            cursor: None,
+            entered_pin: None,
+            visited: vec![],
+            finished: vec![],
+            custom_handlers: HashMap::new(),
+            watchers: HashMap::new(),
+            translations: HashMap::new(),
+            text_overrides: HashMap::new(),
+            localizers: HashMap::new(),
+            locale: None,
+            metrics: Cell::new(Metrics::default()),
+            error_policy: ErrorPolicy::default(),
+            expression_errors: RefCell::new(vec![]),
+            dead_end_policy: DeadEndPolicy::default(),
+            max_technical_depth: 64,
+            hub_stack: vec![],
+            budget: Budget::default(),
+            budget_used: Cell::new(None),
+            coverage: None,
+            transcript: None,
+            local_namespaces: vec![],
+            local_scope: None,
+            auto_advance: None,
+            events: RefCell::new(VecDeque::new()),
+            event_capacity: 64,
+            auto_advanced: vec![],
+        }
+    }
+
+    /// Registers a handler deciding how to traverse `Model::Custom` nodes of the given type (e.g "QuestNode"),
+    /// it's called when advancing from such a node.
+    pub fn on_custom<F>(&mut self, kind: &str, handler: F)
+    where
+        F: FnMut(&Model, &mut HandlerContext) -> Handled + 'static,
+    {
+        self.custom_handlers
+            .insert(kind.to_owned(), Box::new(handler));
+    }
+
+    /// Advances through lines of the given speakers (e.g a narrator) without stopping on them,
+    /// lines offering a choice are still stopped at.
+    pub fn auto_advance_speakers(&mut self, speakers: HashSet<Id>) {
+        self.auto_advance_when(move |speaker| speakers.contains(speaker));
+    }
+
+    /// Same as `auto_advance_speakers`, with a predicate deciding by speaker id
+    pub fn auto_advance_when<F>(&mut self, filter: F)
+    where
+        F: Fn(&Id) -> bool + 'static,
+    {
+        self.auto_advance = Some(Box::new(filter));
+    }
+
+    pub fn clear_auto_advance(&mut self) {
+        self.auto_advance = None;
+    }
+
+    /// Calls `watcher` whenever an Instruction changes the given variable (e.g `relationship.alice`),
+    /// changes made through `set_state` or `import_variables` aren't reported.
+    pub fn watch<F>(&mut self, variable: &str, watcher: F)
+    where
+        F: FnMut(Option<&StateValue>, &StateValue) + 'static,
+    {
+        self.watchers
+            .entry(variable.to_owned())
+            .or_default()
+            .push(Box::new(watcher));
+    }
+
+    pub fn unwatch(&mut self, variable: &str) {
+        self.watchers.remove(variable);
+    }
+
+    pub fn set_state(&mut self, key: &str, value: StateValue) -> Result<(), Error> {
+        self.state
+            .set_value(key.to_owned(), value)
+            .ok()
+            .ok_or(Error::FailedToSetState)
+    }
+
+    pub fn get_state(&self, key: &str) -> Option<&StateValue> {
+        self.state.get_value(key)
+    }
+
+    /// All variables, or only the ones in the given namespace (e.g `Some("quests")` for `quests.*`).
+    /// Local variables (see `local_namespaces`) of the dialogue the flow is in are exported with their values
+    /// from before the dialogue, as that's what they're put back to once it's left.
+    pub fn export_variables(&self, namespace: Option<&str>) -> Variables {
+        let mut variables = self.live_variables(namespace);
+
+        if let Some((_, local)) = &self.local_scope {
+            let prefix = namespace.map(|namespace| format!("{namespace}."));
+            variables.extend(
+                local
+                    .iter()
+                    .filter(|(name, _)| {
+                        prefix
+                            .as_ref()
+                            .is_none_or(|prefix| name.starts_with(prefix))
+                    })
+                    .map(|(name, value)| (name.clone(), value.clone())),
+            );
+        }
+
+        variables
+    }
+
+    /// The variables as they currently are, local variables included
+    fn live_variables(&self, namespace: Option<&str>) -> Variables {
+        let prefix = namespace.map(|namespace| format!("{namespace}."));
+
+        self.state
+            .iter_variables()
+            .filter(|(name, _)| {
+                prefix
+                    .as_ref()
+                    .is_none_or(|prefix| name.starts_with(prefix))
+            })
+            .collect()
+    }
+
+    /// Sets all of the given variables, variables that aren't part of `variables` are left untouched
+    pub fn import_variables(&mut self, variables: &Variables) -> Result<(), Error> {
+        for (name, value) in variables {
+            self.set_state(name, value.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Swaps in a re-exported file while keeping the variables, visited/finished nodes and handlers.
+    /// Variables that are new in the export get their default value, the cursor stays put if its node
+    /// still exists and is cleared otherwise. Returns whether the cursor was kept.
+    pub fn reload(&mut self, file: Arc<File>) -> bool {
+        self.file = file;
+
+        for (name, value) in self.file.clone().default_variables(None) {
+            if self.state.get_value(&name).is_some() {
+                continue;
+            }
+
+            if let Some(value) = to_state_value(value) {
+                let _ = self.state.set_value(name, value);
+            }
+        }
+
+        let file = self.file.clone();
+        self.hub_stack.retain(|(hub, option)| {
+            file.get_model(hub).is_some() && file.get_model(option).is_some()
+        });
+
+        let Some(model) = self
+            .cursor
+            .as_ref()
+            .and_then(|cursor| file.get_model(cursor))
+        else {
+            self.cursor = None;
+            self.entered_pin = None;
+            self.hub_stack.clear();
+
+            return false;
+        };
+
+        let entered_pin_exists = model.input_pins().is_some_and(|pins| {
+            pins.iter()
+                .any(|pin| Some(&pin.id) == self.entered_pin.as_ref())
+        });
+        if !entered_pin_exists {
+            self.entered_pin = None;
+        }
+
+        true
+    }
+
+    /// Clears the cursor and puts every variable back to its exported default,
+    /// visited/finished nodes are kept (see `reset_dialogue`).
+    /// NOTE: The state is rebuilt, so functions set on it directly have to be set again.
+    pub fn reset(&mut self) {
+        self.local_scope = None;
+        self.cursor = None;
+        self.entered_pin = None;
+        self.hub_stack.clear();
+        self.state = HashMapContext::new();
+
+        self.apply_default_variables(None);
+    }
+
+    /// Puts the variables of a single namespace (e.g `chapter1` for `chapter1.*`) back to their exported defaults,
+    /// variables set at runtime that aren't part of the export are removed.
+    pub fn reset_namespace(&mut self, namespace: &str) {
+        let prefix = format!("{namespace}.");
+        let kept = self
+            .state
+            .iter_variables()
+            .filter(|(name, _)| !name.starts_with(&prefix))
+            .collect::<Variables>();
+
+        self.state = HashMapContext::new();
+        for (name, value) in kept {
+            let _ = self.state.set_value(name, value);
+        }
+
+        self.apply_default_variables(Some(namespace));
+    }
+
+    /// Forgets that the dialogue and the nodes inside of it were visited or finished
+    pub fn reset_dialogue(&mut self, dialogue_id: &Id) {
+        let file = self.file.clone();
+        let is_inside = |id: &Id| {
+            let mut cursor = id.clone();
+
+            // NOTE: Bounded in case of a malformed parent chain, the same way as `dialogue_of`
+            for _ in 0..MAX_HIERARCHY_DEPTH {
+                if &cursor == dialogue_id {
+                    return true;
+                }
+
+                match file.get_model(&cursor) {
+                    Some(model) => cursor = model.parent(),
+                    None => return false,
+                }
+            }
+
+            false
+        };
+
+        self.visited.retain(|id| !is_inside(id));
+        self.finished.retain(|id| id != dialogue_id);
+    }
+
+    fn apply_default_variables(&mut self, namespace: Option<&str>) {
+        for (name, value) in self.file.clone().default_variables(namespace) {
+            if let Some(value) = to_state_value(value) {
+                let _ = self.state.set_value(name, value);
+            }
+        }
+    }
+
+    /// Starts the flow at the given node. Containers (FlowFragments, Dialogues) are entered through the
+    /// connections of their input pin, falling back to their first startable child when those aren't connected.
+    /// Nodes that can't be part of a flow (e.g Entities) return `Error::NotStartable`.
+    pub fn start(&mut self, id: Id) -> Result<(), Error> {
+        let steps = self.resolve_entry(&id)?;
+
+        self.hub_stack.clear();
+        for (id, entered_pin) in steps {
+            self.move_cursor(id);
+            self.entered_pin = entered_pin;
+        }
+
+        Ok(())
+    }
+
+    /// The nodes passed through when starting at `id`, ending with the node the flow actually begins at
+    fn resolve_entry(&self, id: &Id) -> Result<Vec<(Id, Option<Id>)>, Error> {
+        let mut steps: Vec<(Id, Option<Id>)> = vec![];
+        let mut step = (self.get_model(id.clone())?.id(), None);
+
+        loop {
+            if steps.iter().any(|(id, _)| *id == step.0) {
+                return Err(Error::NotStartable(step.0));
+            }
+
+            let model = self.get_model(step.0.clone())?;
+            steps.push(step);
+
+            let next = match model {
+                Model::DialogueFragment { .. }
+                | Model::Hub { .. }
+                | Model::Condition { .. }
+                | Model::Instruction { .. } => return Ok(steps),
+
+                Model::FlowFragment { id, .. } | Model::Dialogue { id, .. } => self
+                    .entry_connection(model)
+                    .or_else(|| {
+                        let dialogue = match model {
+                            Model::FlowFragment { .. } => {
+                                self.file.get_dialogues_in_flow(id).first().copied()
+                            }
+                            _ => None,
+                        };
+
+                        dialogue
+                            .map(|dialogue| dialogue.id())
+                            .or_else(|| {
+                                self.file
+                                    .get_first_dialogue_fragment_of_dialogue(model)
+                                    .ok()
+                            })
+                            .map(|id| (id, None))
+                    })
+                    .ok_or(Error::NoHierarchy)?,
+
+                // NOTE: Custom nodes taking part in the flow are handled through `on_custom`
+                Model::Custom(..) if !flow::pins(model, "input_pins").is_empty() => {
+                    return Ok(steps)
+                }
+
+                _ => return Err(Error::NotStartable(model.id())),
+            };
+
+            step = next;
+        }
+    }
+
+    /// Where the connections of the container's input pin lead to
+    fn entry_connection(&self, model: &Model) -> Option<(Id, Option<Id>)> {
+        model
+            .input_pins()?
+            .iter()
+            .flat_map(|pin| &pin.connections)
+            .find(|connection| self.file.get_model(&connection.target).is_some())
+            .map(|connection| {
+                (
+                    connection.target.clone(),
+                    Some(connection.target_pin.clone()),
+                )
+            })
+    }
+
+    /// Moves the cursor straight to any node, e.g to test the middle of a long conversation.
+    /// Input pin conditions are skipped unless `honor_conditions` is set, in which case at least one of the
+    /// node's input pins has to let the flow in.
+    pub fn jump_to(&mut self, id: Id, honor_conditions: bool) -> Result<(), Error> {
+        self.budget_used.set(Some(BudgetUsage::default()));
+        let model = self.get_model(id)?;
+        let id = model.id();
+
+        let mut entered_pin = None;
+
+        if honor_conditions {
+            for pin in model.input_pins().into_iter().flatten() {
+                if pin.text.is_empty()
+                    || self.evaluate_condition(
+                        &id,
+                        ExpressionSource::Pin(pin.id.clone()),
+                        &pin.text,
+                    )?
+                {
+                    entered_pin = Some(pin.id.clone());
+                    break;
+                }
+            }
+
+            if entered_pin.is_none() && model.input_pins().is_some_and(|pins| !pins.is_empty()) {
+                return Err(Error::EntryConditionFailed(id));
+            }
+        }
+
+        self.hub_stack.clear();
+        self.move_cursor(id);
+        self.entered_pin = entered_pin;
+
+        Ok(())
+    }
+
+    /// Uses the translated entries of the table in place of the exported texts
+    pub fn apply_translations(&mut self, table: &StringTable) {
+        self.translations.extend(table.translations());
+    }
+
+    pub fn clear_translations(&mut self) {
+        self.translations.clear();
+    }
+
+    /// Replaces a text field of a node for the current locale without re-exporting, e.g while a writer tweaks lines
+    /// during a playtest. Overrides take precedence over translations and localizers.
+    pub fn set_text_override(&mut self, id: Id, field: TextField, text: String) {
+        self.text_overrides
+            .insert((self.locale.clone(), id, field), text);
+    }
+
+    /// `set_text_override` for many fields at once
+    pub fn set_text_overrides(&mut self, overrides: HashMap<(Id, TextField), String>) {
+        for ((id, field), text) in overrides {
+            self.set_text_override(id, field, text);
+        }
+    }
+
+    /// Removes the override of a text field of the node for the current locale
+    pub fn clear_text_override(&mut self, id: &Id, field: TextField) {
+        self.text_overrides
+            .remove(&(self.locale.clone(), id.clone(), field));
+    }
+
+    /// Removes the overrides of all locales
+    pub fn clear_text_overrides(&mut self) {
+        self.text_overrides.clear();
+    }
+
+    /// Binds a localizer for the given locale, used by the text getters once the locale is set
+    pub fn bind_localizer<L: Localizer + 'static>(&mut self, locale: &str, localizer: L) {
+        self.localizers
+            .insert(locale.to_owned(), Box::new(localizer));
+    }
+
+    /// Switches the locale used by the text getters, `None` goes back to the exported texts
+    pub fn set_locale(&mut self, locale: Option<&str>) {
+        self.locale = locale.map(str::to_owned);
+    }
+
+    pub fn locale(&self) -> Option<&str> {
+        self.locale.as_deref()
+    }
+
+    /// The (translated) text of a field of the given node, text overrides come first
+    /// and translation tables take precedence over the localizer
+    pub fn get_text(&self, id: &Id, field: TextField) -> Option<String> {
+        if let Some(text) = self
+            .text_overrides
+            .get(&(self.locale.clone(), id.clone(), field))
+        {
+            return Some(text.to_owned());
+        }
+
+        if let Some(translation) = self.translations.get(&(id.clone(), field)) {
+            return Some(translation.to_owned());
+        }
+
+        let model = self.file.get_model(id)?;
+
+        self.localize(model, field)
+            .or_else(|| model.text_field(field))
+    }
+
+    /// The text to show for the choice like `ChoiceOption::label`, through the text getters
+    pub fn choice_label(&self, option: &ChoiceOption) -> String {
+        let id = option.model.id();
+
+        ChoiceOption::LABEL_FALLBACK
+            .iter()
+            .filter_map(|field| self.get_text(&id, *field))
+            .find(|text| !text.trim().is_empty())
+            .unwrap_or_default()
+    }
+
+    fn localize(&self, model: &Model, field: TextField) -> Option<String> {
+        let localizer = self.localizers.get(self.locale.as_ref()?)?;
+        let attribute = match field {
+            TextField::Text => None,
+            field => Some(field.name()),
+        };
+
+        model
+            .technical_name()
+            .and_then(|technical_name| localizer.format(&technical_name, attribute, &self.state))
+            .or_else(|| localizer.format(&model.id().0, attribute, &self.state))
+    }
+
+    /// The line at the cursor, `None` if the cursor isn't on a DialogueFragment
+    pub fn current_line(&self) -> Option<LineView> {
+        let fragment = self.get_current_model().ok()?.as_dialogue_fragment()?;
+        let speaker = self.file.get_model(fragment.speaker);
+        let text = |id: &Id, field: TextField| self.get_text(id, field).unwrap_or_default();
+
+        Some(LineView {
+            id: fragment.id.clone(),
+            speaker: speaker.map(Model::id),
+            speaker_name: speaker
+                .and_then(|speaker| self.get_text(&speaker.id(), TextField::DisplayName)),
+            portrait: speaker.and_then(|speaker| self.file.resolve_preview_image(speaker)),
+            text: text(fragment.id, TextField::Text),
+            stage_directions: text(fragment.id, TextField::StageDirections),
+            menu_text: text(fragment.id, TextField::MenuText),
+        })
+    }
+
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.get()
+    }
+
+    pub fn reset_metrics(&mut self) {
+        self.metrics.set(Metrics::default());
+    }
+
+    fn update_metrics(&self, update: impl FnOnce(&mut Metrics)) {
+        let mut metrics = self.metrics.get();
+        update(&mut metrics);
+        self.metrics.set(metrics);
+    }
+
+    /// The last `event_capacity` events, oldest first, e.g to attach to crash reports
+    pub fn recent_events(&self) -> Vec<Event> {
+        self.events.borrow().iter().cloned().collect()
+    }
+
+    fn record_transcript(&mut self, kind: TranscriptEntryKind) {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.push(kind);
+        }
+    }
+
+    pub(crate) fn record_event(&self, kind: EventKind) {
+        let mut events = self.events.borrow_mut();
+
+        events.push_back(Event {
+            at: SystemTime::now(),
+            kind,
+        });
+        while events.len() > self.event_capacity {
+            events.pop_front();
+        }
+    }
+
+    /// The errors kept by `ErrorPolicy::Collect`
+    pub fn take_expression_errors(&mut self) -> Vec<ExpressionError> {
+        self.expression_errors.take()
+    }
+
+    fn evaluate_condition(
+        &self,
+        node: &Id,
+        source: ExpressionSource,
+        expression: &str,
+    ) -> Result<bool, Error> {
+        self.spend_budget(node, &source)?;
+
+        let start = Instant::now();
+        let result = eval_boolean_with_context(expression, &self.state);
+
+        self.update_metrics(|metrics| {
+            metrics.conditions_evaluated += 1;
+            metrics.evaluation_time += start.elapsed();
+            match result {
+                Ok(true) => {}
+                Ok(false) => metrics.condition_failures += 1,
+                Err(_) => metrics.expression_errors += 1,
+            }
+        });
+
+        self.handle_expression_result(node, source, expression, result)
+            .map(Option::unwrap_or_default)
+    }
+
+    fn execute_instruction(
+        &mut self,
+        node: &Id,
+        source: ExpressionSource,
+        expression: &str,
+    ) -> Result<Option<StateValue>, Error> {
+        self.spend_budget(node, &source)?;
+
+        let watched = self
+            .watchers
+            .keys()
+            .map(|variable| (variable.clone(), self.state.get_value(variable).cloned()))
+            .collect::<Vec<_>>();
+        let before = self.transcript.is_some().then(|| self.live_variables(None));
+
+        let start = Instant::now();
+        let result = eval_with_context_mut(expression, &mut self.state);
+
+        self.update_metrics(|metrics| {
+            metrics.instructions_executed += 1;
+            metrics.evaluation_time += start.elapsed();
+            if result.is_err() {
+                metrics.expression_errors += 1;
+            }
+        });
+
+        for (variable, old) in watched {
+            let Some(new) = self.state.get_value(&variable) else {
+                continue;
+            };
+
+            if old.as_ref() != Some(new) {
+                for watcher in self.watchers.get_mut(&variable).into_iter().flatten() {
+                    watcher(old.as_ref(), new);
+                }
+            }
+        }
+
+        if let Some(before) = before {
+            for (name, new) in self.live_variables(None) {
+                let old = before.get(&name);
+
+                if old != Some(&new) {
+                    let old = old.cloned();
+                    self.record_transcript(TranscriptEntryKind::VariableChanged { name, old, new });
+                }
+            }
+        }
+
+        self.handle_expression_result(node, source, expression, result)
+    }
+
+    /// Evaluates the condition of a pin against the current state, without moving the cursor.
+    /// Pins without a condition are open, failing expressions go through the `ErrorPolicy` like any other condition.
+    pub fn evaluate_pin(&self, pin: &Pin) -> Result<bool, Error> {
+        if pin.text.is_empty() {
+            return Ok(true);
+        }
+
+        let usage = self.budget_used.take();
+        let result =
+            self.evaluate_condition(&pin.owner, ExpressionSource::Pin(pin.id.clone()), &pin.text);
+        self.budget_used.set(usage);
+
+        result
+    }
+
+    /// Counts an evaluation against the budget, node expressions also count as a technical node
+    fn spend_budget(&self, node: &Id, source: &ExpressionSource) -> Result<(), Error> {
+        let Some(mut usage) = self.budget_used.get() else {
+            return Ok(());
+        };
+
+        usage.evaluations += 1;
+        if *source == ExpressionSource::Expression {
+            usage.technical_nodes += 1;
+        }
+        self.budget_used.set(Some(usage));
+
+        let exceeds = |used: u32, max: Option<u32>| max.is_some_and(|max| used > max);
+        if exceeds(usage.evaluations, self.budget.max_evaluations)
+            || exceeds(usage.technical_nodes, self.budget.max_technical_nodes)
+        {
+            return Err(Error::BudgetExceeded(node.clone()));
+        }
+
+        Ok(())
+    }
+
+    /// Applies the error policy, `Ok(None)` when the expression failed but traversal should go on
+    fn handle_expression_result<T>(
+        &self,
+        node: &Id,
+        source: ExpressionSource,
+        expression: &str,
+        result: EvalexprResult<T>,
+    ) -> Result<Option<T>, Error> {
+        let error = match result {
+            Ok(value) => return Ok(Some(value)),
+            Err(error) => ExpressionError {
+                node: node.clone(),
+                source,
+                expression: expression.to_owned(),
+                message: error.to_string(),
+            },
+        };
+
+        self.record_event(EventKind::ExpressionFailed(error.clone()));
+
+        match self.error_policy {
+            ErrorPolicy::Ignore => {}
+            ErrorPolicy::Log => eprintln!("[Expression] Failed to evaluate {error:?}"),
+            ErrorPolicy::Collect => self.expression_errors.borrow_mut().push(error),
+            ErrorPolicy::Fail => return Err(Error::Expression(error)),
+        }
+
+        Ok(None)
+    }
+
+    pub fn get_current_model(&self) -> Result<&Model, Error> {
+        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+
+        self.file.get_model(cursor).ok_or(Error::NoModel)
+    }
+
+    pub fn get_model(&self, id: Id) -> Result<&Model, Error> {
+        self.file.get_model(&id).ok_or(Error::NoModel)
+    }
+
+    pub fn get_available_connections_at_cursor(&self) -> Result<Vec<&Model>, Error> {
+        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+        self.get_available_connections(cursor)
+    }
+    /// Evaluations done here don't count against the budget, unless done as part of `advance`/`choose`
+    pub fn get_available_connections(&self, model_id: &Id) -> Result<Vec<&Model>, Error> {
+        let usage = self.budget_used.take();
+        let available = self.available_connections(model_id);
+        self.budget_used.set(usage);
+
+        available
+    }
+
+    fn available_connections(&self, model_id: &Id) -> Result<Vec<&Model>, Error> {
+        Ok(self
+            .open_connections(model_id)?
+            .into_iter()
+            .map(|(_, model)| model)
+            .collect())
+    }
+
+    /// The connections leaving the node whose target pin lets the flow in, with the model they lead to
+    fn open_connections(&self, model_id: &Id) -> Result<Vec<(&Connection, &Model)>, Error> {
+        let model = self.get_model(model_id.clone())?;
+        let mut available = vec![];
+
+        let output_pins = model
+            .output_pins()
+            .ok_or_else(|| Error::MissingPins { id: model.id() })?;
+
+        for pin in output_pins {
+            for connection in &pin.connections {
+                if let Some(target_model) = self.open_target(connection)? {
+                    available.push((connection, target_model));
+                }
+            }
+        }
+
+        Ok(available)
+    }
+
+    /// The model the connection leads to when the pin it ends on lets the flow in
+    fn open_target(&self, connection: &Connection) -> Result<Option<&Model>, Error> {
+        let Some(target_model) = self.file.get_model(&connection.target) else {
+            return Ok(None);
+        };
+
+        let Some(target_pin) = target_model
+            .input_pins()
+            .ok_or_else(|| Error::MissingPins {
+                id: target_model.id(),
+            })?
+            .iter()
+            .find(|pin| pin.id == connection.target_pin)
+        else {
+            // The text of an exit pin is an instruction run on the way out, see `follow_connection`
+            return Ok(exit_pin(target_model, &connection.target_pin).map(|_| target_model));
+        };
+
+        let open = target_pin.text.is_empty()
+            || self.evaluate_condition(
+                &target_model.id(),
+                ExpressionSource::Pin(target_pin.id.clone()),
+                &target_pin.text,
+            )?;
+
+        Ok(open.then_some(target_model))
+    }
+
+    /// Same as `get_available_connections_at_cursor`, along with the Hub at the cursor
+    pub fn get_choice_group(&self) -> Result<ChoiceGroup<'_>, Error> {
+        let hub = match self.get_current_model()? {
+            Model::Hub {
+                id,
+                technical_name,
+                display_name,
+                color,
+                ..
+            } => Some(HubHeader {
+                id,
+                technical_name,
+                display_name,
+                color,
+            }),
+            _ => None,
+        };
+
+        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+        let usage = self.budget_used.take();
+        let open = self.open_connections(cursor);
+        self.budget_used.set(usage);
+        let (connections, choices) = open?.into_iter().unzip();
+
+        Ok(ChoiceGroup {
+            hub,
+            choices,
+            connections,
+        })
+    }
+
+    /// The available connections at the cursor
+    fn get_choices(&self) -> Result<Vec<&Model>, Error> {
+        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+
+        self.available_connections(cursor)
+    }
+
+    pub fn choose(&mut self, id: Id) -> Result<Outcome<'_>, Error> {
+        self.budget_used.set(Some(BudgetUsage::default()));
+        self.auto_advanced.clear();
+        let cursor = self.cursor.clone().ok_or(Error::NoCursor)?;
+        let chosen = self
+            .open_connections(&cursor)?
+            .into_iter()
+            .find(|(_, choice)| choice.id() == id)
+            .map(|(connection, _)| connection.clone());
+
+        match chosen {
+            Some(connection) => {
+                if let Model::Hub { id: hub, .. } = self.get_current_model()? {
+                    let hub = hub.clone();
+                    if let Some(position) = self.hub_stack.iter().position(|(id, _)| *id == hub) {
+                        self.hub_stack.truncate(position);
+                    }
+                    self.hub_stack.push((hub, connection.target.clone()));
+                }
+
+                self.record_event(EventKind::ChoiceMade(connection.target.clone()));
+                if self.transcript.is_some() {
+                    let label = self.get_model(connection.target.clone()).map(|model| {
+                        self.choice_label(&ChoiceOption {
+                            model,
+                            connection: &connection,
+                        })
+                    })?;
+
+                    self.record_transcript(TranscriptEntryKind::Choice {
+                        node: connection.target.clone(),
+                        label,
+                    });
+                }
+                self.follow_connection(connection)?;
+
+                self.post_advance()
+            }
+            None => self.advance(),
+        }
+    }
+
+    pub fn advance(&mut self) -> Result<Outcome<'_>, Error> {
+        self.budget_used.set(Some(BudgetUsage::default()));
+        self.auto_advanced.clear();
+
+        self.step()
+    }
+
+    fn step(&mut self) -> Result<Outcome<'_>, Error> {
+        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+        let model = self.file.get_model(cursor).ok_or(Error::NoModel)?;
+
+        match model {
+            Model::Dialogue { id, .. } => {
+                let id = id.clone();
+                self.mark_finished(id.clone());
+
+                Ok(Outcome::EndOfDialogue {
+                    dialogue: id,
+                    exit_pin: self.entered_pin.clone(),
+                })
+            }
+            Model::DialogueFragment { id, .. } => {
+                let mut open = self
+                    .open_connections(id)?
+                    .into_iter()
+                    .map(|(connection, _)| connection.clone())
+                    .collect::<Vec<Connection>>();
+
+                if open.len() > 1 {
+                    let choices = open
+                        .iter()
+                        .filter_map(|connection| self.file.get_model(&connection.target))
+                        .collect();
+
+                    return Ok(Outcome::WaitingForChoice(choices));
+                }
+
+                // NOTE: Only the connection that's open is followed, closed ones are never taken
+                // even when they come first
+                let Some(next) = open.pop() else {
+                    return self.dead_end();
+                };
+                self.follow_connection(next)?;
+
+                self.post_advance()
+            }
+            // Serves as a point for choices
+            Model::Hub { .. } => {
+                let choices = self.get_choices()?;
+
+                Ok(Outcome::WaitingForChoice(choices))
+            }
+
+            // Conditions are followed by `post_advance`, together with any Conditions chained after them
+            Model::Condition { .. } => self.post_advance(),
+
+            Model::Instruction {
+                id,
+                expression,
+                output_pins,
+                ..
+            } => {
+                let id = id.clone();
+                let expression = expression.clone();
+                let connections = output_pins
+                    .iter()
+                    .flat_map(|pin| pin.connections.clone())
+                    .collect::<Vec<Connection>>();
+
+                self.execute_instruction(&id, ExpressionSource::Expression, &expression)?;
+
+                // NOTE: Instructions don't branch, when fanned out the first connection that's open after
+                // executing is followed and the others are dropped, which is recorded as an event
+                let next = if connections.len() > 1 {
+                    let next = self
+                        .open_connections(&id)?
+                        .first()
+                        .map(|(connection, _)| (*connection).clone());
+
+                    self.record_event(EventKind::BranchesDropped {
+                        node: id.clone(),
+                        followed: next.as_ref().map(|connection| connection.target.clone()),
+                    });
+
+                    next
+                } else {
+                    connections.into_iter().next()
+                };
+
+                let Some(next) = next else {
+                    return self.dead_end();
+                };
+                self.follow_connection(next)?;
+
+                self.post_advance()
+            }
+
+            Model::Custom(kind, _) if self.custom_handlers.contains_key(kind) => {
+                let Some(handler) = self.custom_handlers.get_mut(kind) else {
+                    return Err(Error::UnsupportedNodeType {
+                        id: model.id(),
+                        kind: kind.clone(),
+                    });
+                };
+
+                let handled = handler(
+                    model,
+                    &mut HandlerContext {
+                        file: &self.file,
+                        state: &mut self.state,
+                    },
+                );
+
+                match handled {
+                    Handled::Continue(next_id) => {
+                        self.move_cursor(next_id);
+
+                        self.post_advance()
+                    }
+                    Handled::Stop => Ok(Outcome::Stopped),
+                }
+            }
+
+            // TODO: Implement FlowFragment for triggering things in-game?
+            // Comments, Entities and the like end up here when they're wired into the flow by mistake
+            model => Err(Error::UnsupportedNodeType {
+                id: model.id(),
+                kind: model.type_name().to_owned(),
+            }),
+        }
+    }
+
+    pub fn post_advance(&mut self) -> Result<Outcome<'_>, Error> {
+        self.follow_conditions()?;
+
+        if self.should_auto_advance()? {
+            return self.auto_advance_step();
+        }
+
+        if let Model::Dialogue { id, .. } = self.get_current_model()? {
+            let id = id.clone();
+            self.mark_finished(id);
+        }
+
+        Ok(match self.get_current_model().ok().ok_or(Error::NoModel)? {
+            Model::Dialogue { id, .. } => Outcome::EndOfDialogue {
+                dialogue: id.clone(),
+                exit_pin: self.entered_pin.clone(),
+            },
+            Model::Hub { .. } => {
+                let choices = self.get_choices()?;
+
+                Outcome::WaitingForChoice(choices)
+            }
+            _ => Outcome::Advanced(self.get_current_model().ok().ok_or(Error::NoModel)?),
+        })
+    }
+
+    /// Whether the cursor is on a line of an auto advanced speaker that doesn't offer a choice
+    fn should_auto_advance(&self) -> Result<bool, Error> {
+        let Some(filter) = &self.auto_advance else {
+            return Ok(false);
+        };
+
+        match self.get_current_model()? {
+            Model::DialogueFragment { speaker, .. } if filter(speaker) => {
+                Ok(self.get_choices()?.len() <= 1)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Advances past the line at the cursor, erroring with the lines passed through when
+    /// more than `max_technical_depth` lines in a row are auto advanced
+    fn auto_advance_step(&mut self) -> Result<Outcome<'_>, Error> {
+        let cursor = self.cursor.clone().ok_or(Error::NoCursor)?;
+        self.auto_advanced.push(cursor);
+
+        if self.auto_advanced.len() > self.max_technical_depth {
+            return Err(Error::TraversalLoop(self.auto_advanced.clone()));
+        }
+
+        self.step()
+    }
+
+    /// Evaluates Conditions until the cursor is on another kind of node, erroring with the chain of
+    /// Conditions when there are more than `max_technical_depth` in a row (e.g Conditions wired into a loop)
+    fn follow_conditions(&mut self) -> Result<(), Error> {
+        let file = self.file.clone();
+        let mut chain = vec![];
+
+        while let Model::Condition {
+            id,
+            expression,
+            output_pins,
+            ..
+        } = file
+            .get_model(self.cursor.as_ref().ok_or(Error::NoCursor)?)
+            .ok_or(Error::NoModel)?
+        {
+            chain.push(id.clone());
+            if chain.len() > self.max_technical_depth {
+                return Err(Error::TraversalLoop(chain));
+            }
+
+            let result = self.evaluate_condition(id, ExpressionSource::Expression, expression)?;
+
+            let pin = if result {
+                output_pins.first()
+            } else {
+                output_pins.last()
+            };
+            let next = pin
+                .ok_or(Error::NoOutputConnected)?
+                .connections
+                .first()
+                .ok_or(Error::NoOutputConnected)?
+                .clone();
+
+            self.follow_connection(next)?;
+        }
+
+        Ok(())
+    }
+
+    fn dead_end(&mut self) -> Result<Outcome<'_>, Error> {
+        match (self.dead_end_policy, self.hub_stack.last().cloned()) {
+            (DeadEndPolicy::ReturnToHub, Some((hub, exhausted))) => {
+                self.move_cursor(hub);
+
+                Ok(Outcome::ReturnedToHub {
+                    exhausted,
+                    choices: self.get_choices()?,
+                })
+            }
+            _ => Err(Error::NoOutputConnected),
+        }
+    }
+
+    /// Moves the cursor to the target of the connection, remembering the pin it was entered through.
+    /// Leaving a Dialogue through one of its output pins executes the instruction of that pin.
+    fn follow_connection(&mut self, connection: Connection) -> Result<(), Error> {
+        if let (Some(coverage), Some(cursor)) = (&mut self.coverage, &self.cursor) {
+            coverage.record_connection(cursor, &connection.target_pin);
+        }
+
+        self.move_cursor(connection.target.clone());
+        self.entered_pin = Some(connection.target_pin.clone());
+
+        let file = self.file.clone();
+        if let Some(pin) = file
+            .get_model(&connection.target)
+            .and_then(|model| exit_pin(model, &connection.target_pin))
+            .filter(|pin| !pin.text.is_empty())
+        {
+            self.execute_instruction(
+                &connection.target,
+                ExpressionSource::Pin(pin.id.clone()),
+                &pin.text,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor, keeping track of the visited nodes
+    fn move_cursor(&mut self, id: Id) {
+        self.update_metrics(|metrics| metrics.nodes_advanced += 1);
+
+        if !self.visited.contains(&id) {
+            self.visited.push(id.clone());
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record_node(&id);
+        }
+        self.record_event(EventKind::NodeEntered(id.clone()));
+        if self.transcript.is_some() {
+            if let Some(Model::DialogueFragment { speaker, .. }) = self.file.get_model(&id) {
+                let speaker = self.get_text(speaker, TextField::DisplayName);
+                let text = self.get_text(&id, TextField::Text).unwrap_or_default();
+
+                self.record_transcript(TranscriptEntryKind::Line {
+                    node: id.clone(),
+                    speaker,
+                    text,
+                });
+            }
+        }
+
+        self.enter_local_scope(&id);
+        self.cursor = Some(id);
+        self.entered_pin = None;
+    }
+
+    /// Leaves the local scope of the previous dialogue and enters the one of the dialogue the node is in
+    pub(crate) fn enter_local_scope(&mut self, id: &Id) {
+        if self.local_namespaces.is_empty() {
+            return;
+        }
+
+        let dialogue = self.dialogue_of(id);
+        if self.local_scope.as_ref().map(|(id, _)| id) == dialogue.as_ref() {
+            return;
+        }
+
+        self.leave_local_scope();
+
+        if let Some(dialogue) = dialogue {
+            let variables = self
+                .local_namespaces
+                .iter()
+                .flat_map(|namespace| self.live_variables(Some(namespace)))
+                .collect();
+
+            self.local_scope = Some((dialogue, variables));
+        }
+    }
+
+    /// Puts the local variables back to how they were when the dialogue was entered,
+    /// local variables that didn't exist yet keep their value as evalexpr can't remove them
+    fn leave_local_scope(&mut self) {
+        if let Some((_, variables)) = self.local_scope.take() {
+            for (name, value) in variables {
+                let _ = self.state.set_value(name, value);
+            }
+        }
+    }
+
+    /// The closest Dialogue containing the node
+    fn dialogue_of(&self, id: &Id) -> Option<Id> {
+        let mut parent = self.file.get_model(id)?.parent();
+
+        // NOTE: Bounded in case of a malformed parent chain
+        for _ in 0..MAX_HIERARCHY_DEPTH {
+            match self.file.get_model(&parent)? {
+                Model::Dialogue { id, .. } => return Some(id.clone()),
+                model => parent = model.parent(),
+            }
+        }
+
+        None
+    }
+
+    fn mark_finished(&mut self, dialogue_id: Id) {
+        self.hub_stack.clear();
+        self.leave_local_scope();
+
+        if !self.finished.contains(&dialogue_id) {
+            self.finished.push(dialogue_id);
+        }
+    }
+
+    /// Goes through all of the nodes until meeting some that force it to stop,
+    /// will not tell you what outcome though since that would require looping with a &mut self 😓
+    pub fn exhaust_maximally(&mut self) -> Result<(), Error> {
+        loop {
+            match self.advance()? {
+                // TODO: If there are any state changes applied due to specific node types, be sure to apply them here as well?
+                Outcome::Advanced(..) => continue,
+                _ => break Ok(()),
+            }
+        }
+    }
+}
+
+/// The output pin of the model a connection ends on, lines leaving a Dialogue connect to one of its output pins
+fn exit_pin<'a>(model: &'a Model, pin_id: &Id) -> Option<&'a Pin> {
+    model
+        .output_pins()
+        .into_iter()
+        .flatten()
+        .find(|pin| pin.id == *pin_id)
+}
+
+fn to_state_value(value: &VariableValue) -> Option<StateValue> {
+    match value {
+        VariableValue::Boolean(boolean) => Some(StateValue::Boolean(*boolean)),
+        VariableValue::Integer(integer) => Some(StateValue::Int((*integer).into())),
+        VariableValue::String(string) => Some(StateValue::String(string.clone())),
+        VariableValue::Unknown => None,
+    }
+}
@@ -1,394 +1,2205 @@
+pub mod compat;
+pub mod coverage;
+pub mod extract;
+pub mod flow;
+#[cfg(feature = "interpreter")]
+mod interpreter;
+pub mod loading;
+pub mod localization;
+pub mod locations;
+pub mod persistence;
+pub mod query;
+pub mod stats;
+pub mod symbols;
+#[cfg(feature = "interpreter")]
+pub mod transcript;
 pub mod types;
+pub mod validation;
+pub mod views;
+pub mod visitor;
+pub mod voice_script;
+pub mod warnings;
 
-use std::rc::Rc;
+#[cfg(feature = "interpreter")]
+pub use interpreter::*;
 
-use types::{Error, File, Id, Model, Type};
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::*;
+
+    #[cfg(feature = "interpreter")]
+    use evalexpr::{Context, ContextWithMutableVariables, HashMapContext};
+    #[cfg(feature = "interpreter")]
+    use localization::{Localizer, StringTable, TextField};
+    #[cfg(feature = "interpreter")]
+    use std::{cell::RefCell, rc::Rc, sync::Arc};
+
+    #[test]
+    fn parses_example_project() {
+        let json =
+            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+
+        let _articy_file: File =
+            serde_json::from_str(&json).expect("to be able to parse articy data");
+    }
+
+    #[test]
+    fn get_list_of_objects_definitions() {
+        let json =
+            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+
+        let file: File = serde_json::from_str(&json).expect("to be able to parse articy data");
+
+        for object in &file.object_definitions {
+            println!("{:?}", object.kind)
+        }
+    }
+
+    #[test]
+    fn get_list_of_models() {
+        let json =
+            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+
+        let file: File = serde_json::from_str(&json).expect("to be able to parse articy data");
+
+        let models = file.get_models_of_type("Dialogue");
+
+        println!("models: {models:#?}");
+    }
+
+    fn load_example() -> File {
+        let bytes = std::fs::read("./fixtures/example.json").expect("to be able to read the file");
+
+        File::from_buffer(&bytes)
+    }
+
+    /// The example with the first line offering both the sword line and the gold line,
+    /// the latter only being open once `game.has_gold` is set
+    #[cfg(feature = "interpreter")]
+    fn load_branching_example() -> File {
+        let mut file = load_example();
+        let gold_line = file
+            .get_model(&Id("0x0100000000000304".into()))
+            .unwrap()
+            .input_pins()
+            .unwrap()[0]
+            .clone();
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::DialogueFragment {
+                id, output_pins, ..
+            } = model
+            {
+                if *id == Id("0x0100000000000301".into()) {
+                    let mut to_sword = output_pins[0].connections[0].clone();
+                    to_sword.target = Id("0x0100000000000303".into());
+                    to_sword.target_pin = Id("0x0300000000000303".into());
+
+                    let mut to_gold = to_sword.clone();
+                    to_gold.target = gold_line.owner.clone();
+                    to_gold.target_pin = gold_line.id.clone();
+
+                    output_pins[0].connections = vec![to_gold, to_sword];
+                }
+            }
+        }
+
+        file
+    }
+
+    /// The example with its dialogue nested in one of its own lines
+    fn load_cyclic_example() -> File {
+        let mut file = load_example();
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::Dialogue { parent, .. } = model {
+                *parent = Id("0x0100000000000301".into());
+            }
+        }
+
+        file
+    }
+
+    #[test]
+    fn keeps_template_for_all_models() {
+        let file = load_example();
+
+        let fragment = file
+            .get_models()
+            .into_iter()
+            .find(|model| model.id() == Id("0x0100000000000301".into()))
+            .unwrap();
+        assert_eq!(
+            fragment.template_value("Mood", "Emotion"),
+            Some(&serde_json::json!("Happy"))
+        );
+
+        let entity = file.get_models_of_type("Entity")[0];
+        assert!(entity.template().is_some());
+        assert_eq!(
+            entity.template_value("Character", "Age"),
+            Some(&serde_json::json!(32))
+        );
+
+        let quest = file.get_models_of_type("QuestNode")[0];
+        assert_eq!(
+            quest.template_value("Quest", "QuestName"),
+            Some(&serde_json::json!("Find the sword"))
+        );
+    }
+
+    #[test]
+    fn formats_colors_as_hex() {
+        let color = Color {
+            r: 1.0,
+            g: 0.5,
+            b: 0.0,
+            a: 1.0,
+        };
+        assert_eq!(color.to_hex(), "#FF8000");
+        assert_eq!(Color { a: 0.5, ..color }.to_hex(), "#FF800080");
+
+        let [r, g, b, _] = color.to_linear();
+        assert_eq!((r, b), (1.0, 0.0));
+        assert!((g - 0.214).abs() < 0.001);
+
+        let file = load_example();
+        let hub = file.get_models_of_type("Hub")[0];
+        assert_eq!(hub.position(), Some(Point { x: 250.0, y: 0.0 }));
+        assert_eq!(
+            hub.color().map(|color| color.to_hex()).as_deref(),
+            Some("#808080")
+        );
+        assert!(file.get_models_of_type("QuestNode")[0].size().is_some());
+
+        let layout = hub.layout().unwrap();
+        assert_eq!(layout.position, Point { x: 250.0, y: 0.0 });
+        assert_eq!(layout.split_height, None);
+        let fragment = file.get_model(&Id("0x0100000000000301".into())).unwrap();
+        assert!(fragment.layout().unwrap().split_height.is_some());
+        assert!(file.get_models_of_type("QuestNode")[0].layout().is_some());
+    }
+
+    #[test]
+    fn reads_properties_dynamically() {
+        let file = load_example();
+
+        let fragment = file.get_models_of_type("DialogueFragment")[0];
+        assert_eq!(
+            fragment.get_property("SplitHeight"),
+            Some(serde_json::json!(0.5))
+        );
+        assert_eq!(
+            fragment.get_property("Mood.Emotion"),
+            Some(serde_json::json!("Happy"))
+        );
+
+        let quest = file.get_models_of_type("QuestNode")[0];
+        assert_eq!(
+            quest.get_property("display_name"),
+            Some(serde_json::json!("Find the sword"))
+        );
+        assert_eq!(quest.get_property("missing"), None);
+    }
+
+    #[test]
+    fn registers_custom_model_types() {
+        #[derive(serde::Deserialize)]
+        struct QuestNode {
+            display_name: String,
+        }
+
+        let mut file = load_example();
+        assert_eq!(
+            file.register_model_type::<QuestNode>("QuestNode").unwrap(),
+            1
+        );
 
-pub use evalexpr::Value as StateValue;
-use evalexpr::{
-    eval_boolean_with_context, eval_with_context_mut, Context, ContextWithMutableVariables,
-    HashMapContext,
-};
+        let quest_id = file.get_models_of_type("QuestNode")[0].id();
+        let quest = file.get_custom_model::<QuestNode>(&quest_id).unwrap();
+        assert_eq!(quest.display_name, "Find the sword");
+        assert_eq!(file.get_custom_models::<QuestNode>().len(), 1);
+        assert!(file.get_custom_model::<String>(&quest_id).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn advances_custom_nodes_through_handlers() {
+        let file = load_example();
+        let quest_id = file.get_models_of_type("QuestNode")[0].id();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        let next_id = dialogue_id.clone();
+        interpreter.on_custom("QuestNode", move |_, context| {
+            let _ = context
+                .state
+                .set_value("quest.stage".into(), StateValue::Int(1));
+
+            Handled::Continue(next_id.clone())
+        });
+
+        interpreter.start(quest_id).unwrap();
+        assert!(matches!(
+            interpreter.advance().unwrap(),
+            Outcome::EndOfDialogue { .. }
+        ));
+        assert_eq!(interpreter.cursor, Some(dialogue_id));
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(1))
+        );
+    }
+
+    #[test]
+    fn iterates_flow_depth_first() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let technical_names = file
+            .iter_flow_from(&dialogue_id)
+            .map(|model| model.get_property("technical_name").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            technical_names,
+            [
+                "Intro_Dialogue",
+                "DFr_301",
+                "Hub_Choice",
+                "DFr_303",
+                "Ins_TakeSword",
+                "Cnd_HasSword",
+                "DFr_307",
+                "DFr_308",
+                "DFr_304"
+            ]
+        );
+
+        assert_eq!(
+            file.iter_flow_from(&dialogue_id).with_max_depth(2).count(),
+            3
+        );
+        assert_eq!(
+            file.iter_flow_from(&dialogue_id)
+                .with_filter(|model| !matches!(model, Model::Hub { .. }))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn finds_shortest_paths() {
+        let file = load_example();
+        let hub = Id("0x0100000000000302".into());
+        let suit_yourself = Id("0x0100000000000308".into());
+
+        assert_eq!(file.find_path(&hub, &suit_yourself).unwrap().len(), 3);
+        assert_eq!(file.find_path(&suit_yourself, &hub), None);
+
+        let mut state = HashMapContext::new();
+        state
+            .set_value("game.has_gold".into(), StateValue::Boolean(false))
+            .unwrap();
+        assert_eq!(
+            file.find_path_with_state(&hub, &suit_yourself, &state)
+                .unwrap()
+                .len(),
+            5
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn extracts_dialogue_subtree() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let extracted = file.extract_subtree(&dialogue_id);
+        let json = serde_json::to_string(&extracted).unwrap();
+        let extracted: File = serde_json::from_str(&json).unwrap();
+
+        // The dialogue with its 8 nodes, the FlowFragment it's in, both speakers and their folder,
+        // Alice's preview image asset and its folder
+        assert_eq!(extracted.get_models().len(), 15);
+        assert!(extracted.get_models_of_type("QuestNode").is_empty());
+        assert_eq!(extracted.global_variables.len(), 1);
+
+        let mut interpreter = Interpreter::new(extracted.into());
+        interpreter.start(dialogue_id).unwrap();
+        assert_eq!(
+            interpreter.get_current_model().unwrap().id(),
+            Id("0x0100000000000301".into())
+        );
+    }
+
+    #[test]
+    fn queries_models() {
+        let file = load_example();
+        let alice = Id("0x0100000000000401".into());
+        let chapter = Id("0x0100000000000100".into());
+
+        let lines = file
+            .query()
+            .of_type(Type::DialogueFragment)
+            .with_speaker(&alice)
+            .in_flow(&chapter)
+            .collect();
+        assert_eq!(lines.len(), 3);
+
+        let sword = file.query().text_contains("sword").collect();
+        assert_eq!(sword.len(), 1);
+        assert_eq!(sword[0].id(), Id("0x0100000000000303".into()));
+
+        assert_eq!(
+            file.query()
+                .of_type(Type::Custom("QuestNode".into()))
+                .collect()
+                .len(),
+            1
+        );
+
+        let file = load_cyclic_example();
+        assert!(file
+            .query()
+            .of_type(Type::DialogueFragment)
+            .in_flow(&chapter)
+            .collect()
+            .is_empty());
+    }
+
+    #[test]
+    fn visits_hierarchy_and_models() {
+        #[derive(Default)]
+        struct Counter<'a> {
+            depth: usize,
+            max_depth: usize,
+            fragments: Vec<&'a Model>,
+        }
+
+        impl<'a> visitor::Visitor<'a> for Counter<'a> {
+            fn enter_node(&mut self, _node: &'a Hierarchy, depth: usize) {
+                assert_eq!(self.depth, depth);
+                self.depth += 1;
+                self.max_depth = self.max_depth.max(depth);
+            }
+
+            fn leave_node(&mut self, _node: &'a Hierarchy, _depth: usize) {
+                self.depth -= 1;
+            }
+
+            fn visit_model(&mut self, model: &'a Model, _node: &'a Hierarchy) {
+                if let Model::DialogueFragment { .. } = model {
+                    self.fragments.push(model);
+                }
+            }
+        }
+
+        let file = load_example();
+        let mut counter = Counter::default();
+        file.accept(&mut counter);
+
+        assert_eq!(counter.depth, 0);
+        assert_eq!(counter.max_depth, 4);
+        assert_eq!(counter.fragments.len(), 5);
+    }
+
+    #[test]
+    fn reports_dialogue_branching() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let report = stats::dialogue_report(&file, &dialogue_id).unwrap();
+        assert_eq!(report.node_count, 8);
+        assert_eq!((report.choice_points, report.choices), (1, 2));
+        assert_eq!(
+            (report.shortest_path, report.longest_path),
+            (Some(4), Some(6))
+        );
+
+        assert_eq!(report.endings.len(), 2);
+        let peaceful = &report.endings[0];
+        assert_eq!(peaceful.exit_pin, Id("0x0200000000000201".into()));
+        assert_eq!(peaceful.conditions, ["game.sword_taken == true"]);
+        let other = &report.endings[1];
+        assert_eq!(other.shortest_path, 4);
+        assert_eq!(other.conditions, ["game.has_gold == true"]);
+        assert!(!report.truncated);
+
+        let truncated = stats::dialogue_report_with_limit(&file, &dialogue_id, 3).unwrap();
+        assert!(truncated.truncated);
+        assert!(truncated.endings.is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn round_trips_string_tables() {
+        let file = load_example();
+        let take_sword = Id("0x0100000000000303".into());
+
+        let mut table = StringTable::extract(&file);
+        assert!(table
+            .entries
+            .iter()
+            .any(|entry| entry.id == take_sword && entry.field == TextField::StageDirections));
+
+        for entry in &mut table.entries {
+            if entry.id == take_sword && entry.field == TextField::Text {
+                entry.translation = "Ich nehme das Schwert, \"danke\".".into();
+            }
+        }
+
+        assert_eq!(StringTable::from_csv(&table.to_csv()).unwrap(), table);
+        assert_eq!(
+            StringTable::from_xliff(&table.to_xliff("en", "de")).unwrap(),
+            table
+        );
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.apply_translations(&table);
+        assert_eq!(
+            interpreter.get_text(&take_sword, TextField::Text).unwrap(),
+            "Ich nehme das Schwert, \"danke\"."
+        );
+        assert_eq!(
+            interpreter
+                .get_text(&take_sword, TextField::MenuText)
+                .unwrap(),
+            "Take the sword"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn localizes_text_through_bound_localizer() {
+        struct German;
+
+        impl Localizer for German {
+            fn format(
+                &self,
+                message: &str,
+                attribute: Option<&str>,
+                variables: &HashMapContext,
+            ) -> Option<String> {
+                let gold = variables.get_value("game.gold")?;
+
+                match (message, attribute) {
+                    ("DFr_301", None) => Some(format!("Hallo, du hast {gold} Gold.")),
+                    _ => None,
+                }
+            }
+        }
+
+        let mut interpreter = Interpreter::new(load_example().into());
+        let greeting = Id("0x0100000000000301".into());
+        interpreter
+            .set_state("game.gold", StateValue::Int(10))
+            .unwrap();
+        interpreter.bind_localizer("de", German);
+
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hello there, traveller."
+        );
+
+        interpreter.set_locale(Some("de"));
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hallo, du hast 10 Gold."
+        );
+        assert_eq!(
+            interpreter
+                .get_text(&Id("0x0100000000000303".into()), TextField::Text)
+                .unwrap(),
+            "I'll take the sword."
+        );
+    }
+
+    #[test]
+    fn exports_voice_scripts_per_speaker() {
+        let file = load_example();
+        let scripts = voice_script::voice_scripts(&file);
+
+        let alice = &scripts[0];
+        assert_eq!(alice.speaker_name, "Alice");
+        assert_eq!(alice.scenes.len(), 1);
+        assert_eq!(alice.scenes[0].title, "Chapter One / Intro");
+        assert_eq!(alice.scenes[0].lines.len(), 3);
+
+        let bob = &scripts[1];
+        assert!(bob
+            .to_markdown()
+            .contains("- `0x0100000000000303` *(reaching for the blade)* I'll take the sword."));
+        assert_eq!(bob.to_csv().lines().count(), 3);
+
+        let scripts = voice_script::voice_scripts(&load_cyclic_example());
+        assert_eq!(scripts[0].scenes[0].lines.len(), 3);
+    }
+
+    #[test]
+    fn lints_text_content() {
+        let file = load_example();
+
+        let issues = validation::validate(&file, &validation::ValidationConfig::default());
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].id, Id("0x0100000000000304".into()));
+        assert_eq!(issues[0].kind, validation::IssueKind::PlaceholderText);
+
+        let config = validation::ValidationConfig {
+            placeholder_patterns: vec![],
+            max_menu_text_length: 5,
+        };
+        let issues = validation::validate(&file, &config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].kind, validation::IssueKind::MenuTextTooLong);
+    }
+
+    #[test]
+    fn projects_typed_views() {
+        let file = load_example();
+
+        assert_eq!(
+            file.get_models_of_type(Type::Dialogue).len(),
+            file.get_models_of_type("Dialogue").len()
+        );
+
+        let dialogue = file.dialogues().next().unwrap();
+        assert_eq!(dialogue.display_name, "Intro");
+        assert_eq!(dialogue.output_pins.len(), 2);
+
+        let fragment = file.dialogue_fragments().nth(1).unwrap();
+        assert_eq!(fragment.menu_text, "Take the sword");
+        assert_eq!(fragment.speaker, &Id("0x0100000000000402".into()));
+        assert!(fragment.model.as_dialogue().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn exports_variables_by_namespace() {
+        let mut interpreter = Interpreter::new(load_example().into());
+        interpreter
+            .set_state("quests.stage", StateValue::Int(2))
+            .unwrap();
+        interpreter
+            .set_state("quests.name", StateValue::String("Sword".into()))
+            .unwrap();
+        interpreter
+            .set_state("game.gold", StateValue::Int(10))
+            .unwrap();
+
+        let quests = interpreter.export_variables(Some("quests"));
+        assert_eq!(quests.len(), 2);
+        assert_eq!(interpreter.export_variables(None).len(), 3);
+
+        let json = serde_json::to_string(&quests).unwrap();
+        let quests: Variables = serde_json::from_str(&json).unwrap();
+
+        let mut restored = Interpreter::new(load_example().into());
+        restored.import_variables(&quests).unwrap();
+        assert_eq!(
+            restored.get_state("quests.stage"),
+            Some(&StateValue::Int(2))
+        );
+        assert_eq!(restored.get_state("game.gold"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn persists_seen_content_across_reexports() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.exhaust_maximally().unwrap();
+        interpreter.advance().unwrap();
+        assert_eq!(interpreter.finished, [dialogue_id]);
+
+        let seen = interpreter.export_seen(persistence::PersistenceKey::TechnicalName);
+        assert!(seen.visited.contains(&"DFr_303".to_owned()));
+
+        // Re-export where Articy shuffled the id of the chosen line
+        let json = std::fs::read_to_string("./fixtures/example.json")
+            .unwrap()
+            .replace("0x0100000000000303", "0x01000000000009FF");
+        let mut restored = Interpreter::new(File::from_buffer(json.as_bytes()).into());
+
+        assert!(restored.import_seen(&seen).is_empty());
+        assert!(restored.visited.contains(&Id("0x01000000000009FF".into())));
+        assert_eq!(restored.finished.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn counts_runtime_metrics() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.advance().unwrap();
+
+        let metrics = interpreter.metrics();
+        assert_eq!(metrics.instructions_executed, 0);
+        // The shield line's pin condition can't be evaluated without game.has_gold
+        assert!(metrics.expression_errors >= 1);
+        // Kept by the default policy rather than printed
+        assert!(!interpreter.take_expression_errors().is_empty());
+
+        interpreter.advance().unwrap();
+        let metrics = interpreter.metrics();
+        assert_eq!(metrics.instructions_executed, 1);
+        assert_eq!(metrics.conditions_evaluated, metrics.expression_errors + 1);
+        assert_eq!(metrics.nodes_advanced, 7);
+
+        interpreter.reset_metrics();
+        assert_eq!(interpreter.metrics(), Metrics::default());
+
+        // The pin condition of the option chosen counts once
+        interpreter.start(dialogue_id).unwrap();
+        interpreter
+            .set_state("game.has_gold", StateValue::Boolean(true))
+            .unwrap();
+        interpreter.advance().unwrap();
+        interpreter.reset_metrics();
+        interpreter.choose(Id("0x0100000000000304".into())).unwrap();
+
+        let metrics = interpreter.metrics();
+        assert_eq!(metrics.conditions_evaluated, 1);
+        assert_eq!(metrics.condition_failures, 0);
+        assert_eq!(metrics.expression_errors, 0);
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn surfaces_expression_errors_through_policy() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.error_policy = ErrorPolicy::Fail;
+        interpreter.start(dialogue_id).unwrap();
+
+        let Err(Error::Expression(error)) = interpreter.advance() else {
+            panic!("expected the shield line's pin condition to fail");
+        };
+        assert_eq!(error.node, Id("0x0100000000000304".into()));
+        assert_eq!(
+            error.source,
+            ExpressionSource::Pin(Id("0x0300000000000304".into()))
+        );
+        assert_eq!(error.expression, "game.has_gold == true");
+
+        interpreter.error_policy = ErrorPolicy::Collect;
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(_))
+        ));
+        assert_eq!(interpreter.take_expression_errors().len(), 1);
+        assert!(interpreter.take_expression_errors().is_empty());
+    }
+
+    #[test]
+    fn resolves_preview_images_to_assets() {
+        let file = load_example();
+        let alice = file.get_model(&Id("0x0100000000000401".into())).unwrap();
+        let bob = file.get_model(&Id("0x0100000000000402".into())).unwrap();
+
+        let resolved = file.resolve_preview_image(alice).unwrap();
+        assert_eq!(resolved.asset, Id("0x0100000000000601".into()));
+        assert_eq!(resolved.path, "Assets/Characters/alice.png");
+        assert_eq!(resolved.view_box.w, 128.0);
+
+        assert!(file.resolve_preview_image(bob).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn evaluates_the_input_pin_that_was_entered() {
+        let mut file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+        let shield_line = Id("0x0100000000000304".into());
+        let unconditional_pin = Id("0x0310000000000304".into());
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            match model {
+                Model::DialogueFragment { id, input_pins, .. } if *id == shield_line => {
+                    input_pins.push(Pin {
+                        text: String::new(),
+                        id: unconditional_pin.clone(),
+                        owner: shield_line.clone(),
+                        connections: vec![],
+                    });
+                }
+                Model::Hub { output_pins, .. } => output_pins[0].connections.push(Connection {
+                    label: String::new(),
+                    target_pin: unconditional_pin.clone(),
+                    target: shield_line.clone(),
+                    color: None,
+                    extra: Default::default(),
+                }),
+                _ => {}
+            }
+        }
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.error_policy = ErrorPolicy::Ignore;
+        interpreter.start(dialogue_id).unwrap();
+        interpreter.advance().unwrap();
+
+        // The first input pin requires game.has_gold, the second one doesn't
+        assert!(matches!(
+            interpreter.choose(shield_line.clone()),
+            Ok(Outcome::Advanced(model)) if model.id() == shield_line
+        ));
+        assert_eq!(interpreter.entered_pin, Some(unconditional_pin));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn returns_to_hub_after_dead_end() {
+        let mut file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+        let hub = Id("0x0100000000000302".into());
+        let sword_line = Id("0x0100000000000303".into());
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::DialogueFragment {
+                id, output_pins, ..
+            } = model
+            {
+                if *id == sword_line {
+                    output_pins[0].connections.clear();
+                }
+            }
+        }
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.error_policy = ErrorPolicy::Ignore;
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(sword_line.clone()).unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Err(Error::NoOutputConnected)
+        ));
+
+        interpreter.dead_end_policy = DeadEndPolicy::ReturnToHub;
+        let Ok(Outcome::ReturnedToHub { exhausted, choices }) = interpreter.advance() else {
+            panic!("expected to return to the hub");
+        };
+        assert_eq!(exhausted, sword_line);
+        assert!(!choices.is_empty());
+        assert_eq!(interpreter.cursor, Some(hub));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn resets_state_namespaces_and_dialogues() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(10))
+        );
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(0))
+        );
+
+        interpreter
+            .set_state("game.gold", StateValue::Int(3))
+            .unwrap();
+        interpreter
+            .set_state("quest.stage", StateValue::Int(2))
+            .unwrap();
+        interpreter
+            .set_state("quest.extra", StateValue::Boolean(true))
+            .unwrap();
+        interpreter.reset_namespace("quest");
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(3))
+        );
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(0))
+        );
+        assert_eq!(interpreter.get_state("quest.extra"), None);
+
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.exhaust_maximally().unwrap();
+        interpreter.advance().unwrap();
+        assert_eq!(interpreter.finished, vec![dialogue_id.clone()]);
+
+        interpreter.reset_dialogue(&dialogue_id);
+        assert!(interpreter.finished.is_empty());
+        assert!(interpreter.visited.is_empty());
+
+        // The depth limit of traversal doesn't bound the hierarchy
+        interpreter.max_technical_depth = 1;
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.reset_dialogue(&Id("0x0100000000000100".into()));
+        assert!(interpreter.visited.is_empty());
+
+        interpreter.reset();
+        assert_eq!(interpreter.cursor, None);
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(10))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn notifies_watchers_of_changed_variables() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+        let changes = Rc::new(RefCell::new(vec![]));
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+
+        let sword_changes = changes.clone();
+        interpreter.watch("game.sword_taken", move |old, new| {
+            sword_changes.borrow_mut().push((old.cloned(), new.clone()));
+        });
+        let gold_changes = changes.clone();
+        interpreter.watch("game.gold", move |old, new| {
+            gold_changes.borrow_mut().push((old.cloned(), new.clone()));
+        });
+
+        interpreter.start(dialogue_id).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.exhaust_maximally().unwrap();
+
+        assert_eq!(
+            *changes.borrow(),
+            vec![(Some(StateValue::Boolean(false)), StateValue::Boolean(true))]
+        );
+    }
+
+    #[test]
+    fn shares_repeated_ids() {
+        let file = load_example();
+        let alice = file.get_model(&Id("0x0100000000000401".into())).unwrap();
+        let Some(Model::DialogueFragment { speaker, .. }) =
+            file.get_model(&Id("0x0100000000000301".into()))
+        else {
+            panic!("expected the first line to be a DialogueFragment");
+        };
+
+        assert_eq!(*speaker, alice.id());
+        assert!(std::sync::Arc::ptr_eq(&speaker.0, &alice.id().0));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn jumps_to_nodes_by_id_or_technical_name() {
+        let file = load_example();
+        let shield_line = file.find_model("DFr_304").unwrap().id();
+        assert_eq!(shield_line, Id("0x0100000000000304".into()));
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+
+        assert!(matches!(
+            interpreter.jump_to(shield_line.clone(), true),
+            Err(Error::EntryConditionFailed(id)) if id == shield_line
+        ));
+        assert_eq!(interpreter.cursor, None);
+
+        interpreter.jump_to(shield_line.clone(), false).unwrap();
+        assert_eq!(interpreter.cursor, Some(shield_line.clone()));
+
+        interpreter
+            .set_state("game.has_gold", StateValue::Boolean(true))
+            .unwrap();
+        interpreter.jump_to(shield_line, true).unwrap();
+        assert_eq!(
+            interpreter.entered_pin,
+            Some(Id("0x0300000000000304".into()))
+        );
+    }
+
+    #[test]
+    fn searches_text_fields() {
+        let mut file = load_example();
+
+        let hits = file.search("SWORD");
+        assert_eq!(hits.len(), 2);
+        assert!(hits
+            .iter()
+            .all(|hit| hit.model.id() == Id("0x0100000000000303".into())));
+        assert_eq!(hits[1].field, localization::TextField::MenuText);
+        assert!(file.search("").is_empty());
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::DialogueFragment { id, text, .. } = model {
+                if id.as_str() == "0x0100000000000308" {
+                    *text = format!("{}needle{}", "a".repeat(40), "b".repeat(40));
+                }
+            }
+        }
+
+        let hits = file.search("needle");
+        assert_eq!(
+            hits[0].snippet,
+            format!("…{}needle{}…", "a".repeat(30), "b".repeat(30))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn reloads_file_keeping_state() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+        let hub = Id("0x0100000000000302".into());
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+        interpreter
+            .set_state("game.gold", StateValue::Int(3))
+            .unwrap();
+        interpreter.start(dialogue_id).unwrap();
+        interpreter.advance().unwrap();
+
+        let mut reexport = load_example();
+        let has_gold = reexport.global_variables[0].variables[0].clone();
+        reexport.global_variables[1].variables.push(has_gold);
+        assert!(interpreter.reload(reexport.into()));
+        assert_eq!(interpreter.cursor, Some(hub.clone()));
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(3))
+        );
+        assert_eq!(
+            interpreter.get_state("quest.has_gold"),
+            Some(&StateValue::Boolean(false))
+        );
+
+        let mut reexport = load_example();
+        for package in &mut reexport.packages {
+            package.models.retain(|model| model.id() != hub);
+        }
+        assert!(!interpreter.reload(reexport.into()));
+        assert_eq!(interpreter.cursor, None);
+        assert!(interpreter.visited.contains(&hub));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn starts_on_any_startable_node() {
+        let file = load_example();
+        let chapter = Id("0x0100000000000100".into());
+        let dialogue = Id("0x0100000000000200".into());
+        let hub = Id("0x0100000000000302".into());
+        let alice = Id("0x0100000000000401".into());
+
+        let mut interpreter = Interpreter::new(file.into());
+
+        interpreter.start(chapter.clone()).unwrap();
+        assert_eq!(interpreter.cursor, Some(Id("0x0100000000000301".into())));
+        assert_eq!(interpreter.visited[..2], [chapter, dialogue]);
+        assert_eq!(
+            interpreter.entered_pin,
+            Some(Id("0x0300000000000301".into()))
+        );
+
+        interpreter.start(hub.clone()).unwrap();
+        assert_eq!(interpreter.cursor, Some(hub));
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(_))
+        ));
+
+        interpreter.start(Id("0x0100000000000305".into())).unwrap();
+        assert!(matches!(interpreter.advance(), Ok(Outcome::Advanced(_))));
+
+        assert!(matches!(
+            interpreter.start(alice.clone()),
+            Err(Error::NotStartable(id)) if id == alice
+        ));
+    }
+
+    #[test]
+    fn lists_dialogue_endings() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let endings = file
+            .get_endings(&dialogue_id)
+            .into_iter()
+            .map(|ending| (ending.node.id(), ending.exit_pin))
+            .collect::<Vec<(Id, Id)>>();
+
+        assert_eq!(
+            endings,
+            vec![
+                (
+                    Id("0x0100000000000307".into()),
+                    Id("0x0200000000000201".into())
+                ),
+                (
+                    Id("0x0100000000000308".into()),
+                    Id("0x0200000000000202".into())
+                ),
+            ]
+        );
+        assert!(file
+            .get_endings(&Id("0x0100000000000401".into()))
+            .is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn reports_the_exit_pin_of_a_dialogue() {
+        let file = load_example();
+        let dialogue_id = file.get_models_of_type("Dialogue")[0].id();
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+        interpreter.start(dialogue_id.clone()).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.exhaust_maximally().unwrap();
+
+        let Ok(Outcome::EndOfDialogue { dialogue, exit_pin }) = interpreter.advance() else {
+            panic!("expected the dialogue to end");
+        };
+        assert_eq!(dialogue, dialogue_id);
+        assert_eq!(exit_pin, Some(Id("0x0200000000000201".into())));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn guards_against_condition_loops() {
+        let mut file = load_example();
+        let condition = Id("0x0100000000000306".into());
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::Condition {
+                id, output_pins, ..
+            } = model
+            {
+                if *id == condition {
+                    for pin in output_pins {
+                        pin.connections = vec![Connection {
+                            label: String::new(),
+                            target_pin: Id("0x0300000000000306".into()),
+                            target: condition.clone(),
+                            color: None,
+                            extra: Default::default(),
+                        }];
+                    }
+                }
+            }
+        }
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.reset();
+        interpreter.max_technical_depth = 8;
+        interpreter.jump_to(condition.clone(), false).unwrap();
+
+        let Err(Error::TraversalLoop(chain)) = interpreter.advance() else {
+            panic!("expected the looping Condition to be caught");
+        };
+        assert_eq!(chain.len(), 9);
+        assert!(chain.iter().all(|id| *id == condition));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn overrides_text_per_locale() {
+        let file = load_example();
+        let greeting = Id("0x0100000000000301".into());
+
+        let mut interpreter = Interpreter::new(file.into());
+        interpreter.set_text_override(greeting.clone(), TextField::Text, "Well met.".into());
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Well met."
+        );
+
+        interpreter.set_locale(Some("de"));
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hello there, traveller."
+        );
+        interpreter.set_text_overrides(std::collections::HashMap::from([(
+            (greeting.clone(), TextField::Text),
+            "Hallo!".into(),
+        )]));
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hallo!"
+        );
+
+        interpreter.clear_text_override(&greeting, TextField::Text);
+        interpreter.set_locale(None);
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Well met."
+        );
+        interpreter.clear_text_overrides();
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hello there, traveller."
+        );
+
+        // Every field is overridden on its own
+        interpreter.set_text_override(greeting.clone(), TextField::MenuText, "Greet".into());
+        assert_eq!(
+            interpreter
+                .get_text(&greeting, TextField::MenuText)
+                .unwrap(),
+            "Greet"
+        );
+        assert_eq!(
+            interpreter.get_text(&greeting, TextField::Text).unwrap(),
+            "Hello there, traveller."
+        );
+    }
 
-pub struct Interpreter {
-    pub file: Rc<File>,
-    pub state: HashMapContext,
-    pub visited: Vec<Id>,
-    pub finished: Vec<Id>,
-    pub cursor: Option<Id>,
-}
+    #[test]
+    fn exposes_raw_json_of_models() {
+        let file = load_example();
+        let quest = file.get_model(&Id("0x0100000000000500".into())).unwrap();
+        let greeting = file.get_model(&Id("0x0100000000000301".into())).unwrap();
 
-#[derive(Debug, Clone)]
-pub enum Outcome<'a> {
-    Advanced(&'a Model),
-    WaitingForChoice(Vec<&'a Model>),
-    Stopped,
-    EndOfDialogue,
-}
+        assert_eq!(quest.raw().unwrap()["technical_name"], "Quest_FindSword");
 
-impl Interpreter {
-    pub fn new(file: Rc<File>) -> Self {
-        Interpreter {
-            file,
-            state: HashMapContext::new(),
-            cursor: None,
-            visited: vec![],
-            finished: vec![],
-        }
+        #[cfg(feature = "raw-json")]
+        assert_eq!(
+            greeting.raw().unwrap()["properties"]["speaker"],
+            "0x0100000000000401"
+        );
+        #[cfg(not(feature = "raw-json"))]
+        assert!(greeting.raw().is_none());
     }
 
-    pub fn set_state(&mut self, key: &str, value: StateValue) -> Result<(), Error> {
-        self.state
-            .set_value(key.to_owned(), value)
-            .ok()
-            .ok_or(Error::FailedToSetState)
+    #[test]
+    fn parses_draft_3_and_x_exports_alike() {
+        // NOTE: example_x.json is example.json carried over to the X format by hand,
+        // it's to be replaced by an export straight out of articy:draft X
+        let draft_3 = load_example();
+        let bytes =
+            std::fs::read("./fixtures/example_x.json").expect("to be able to read the file");
+        let draft_x = File::from_buffer(&bytes);
+
+        assert_eq!(draft_3.export_format(), compat::ExportFormat::Draft3);
+        assert_eq!(draft_x.export_format(), compat::ExportFormat::DraftX);
+
+        assert_eq!(
+            draft_x.object_definitions.len(),
+            draft_3.object_definitions.len()
+        );
+        assert!(draft_x
+            .get_default_package()
+            .models
+            .iter()
+            .zip(&draft_3.get_default_package().models)
+            .all(|(x, three)| x.type_name() == three.type_name() && x.id() == three.id()));
+
+        // Renamed from ZOrder
+        let greeting = Id("0x0100000000000301".into());
+        assert_eq!(
+            draft_x
+                .get_model(&greeting)
+                .unwrap()
+                .layout()
+                .unwrap()
+                .z_index,
+            3.0
+        );
+        assert_eq!(
+            draft_3
+                .get_model(&greeting)
+                .unwrap()
+                .layout()
+                .unwrap()
+                .z_index,
+            0.0
+        );
     }
 
-    pub fn get_state(&self, key: &str) -> Option<&StateValue> {
-        self.state.get_value(key)
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn builds_the_current_line() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+
+        let line = interpreter.current_line().unwrap();
+        assert_eq!(line.speaker, Some(Id("0x0100000000000401".into())));
+        assert_eq!(line.speaker_name.as_deref(), Some("Alice"));
+        assert_eq!(
+            line.portrait.map(|portrait| portrait.path).as_deref(),
+            Some("Assets/Characters/alice.png")
+        );
+        assert_eq!(line.text, "Hello there, traveller.");
+
+        interpreter.set_text_override(
+            Id("0x0100000000000301".into()),
+            TextField::Text,
+            "Hi.".to_owned(),
+        );
+        assert_eq!(interpreter.current_line().unwrap().text, "Hi.");
+
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        assert!(interpreter.current_line().is_none());
     }
 
-    pub fn start<'a>(&mut self, id: Id) -> Result<(), Error> {
-        self.cursor = Some(
-            self.file
-                .get_default_package()
-                .models
-                .iter()
-                .find(|model| model.id() == id)
-                .ok_or(Error::NoModel)?
-                .id()
-                .clone(),
-        );
-
-        match self.get_current_model() {
-            Ok(Model::FlowFragment { id, .. }) => {
-                let dialogue = self
-                    .file
-                    .get_dialogues_in_flow(&id)
-                    .first()
-                    .ok_or(Error::NoModel)?
-                    .to_owned()
-                    .clone();
-
-                let path = self.file.get_hierarchy_path_from_model(&dialogue)?;
-
-                // FIXME: Maybe dont assume we'll start with a piece of dialogue?
-                let start_dialogue_fragment_id = self
-                    .file
-                    .get_hierarchy(path)
-                    .ok_or(Error::NoHierarchy)?
-                    .children
-                    .as_ref()
-                    .ok_or(Error::NoHierarchy)?
-                    .iter()
-                    .find(|node| match node.kind {
-                        Type::DialogueFragment
-                        | Type::Condition
-                        | Type::Hub
-                        | Type::FlowFragment => true,
-                        _ => false,
-                    })
-                    .ok_or(Error::NoHierarchy)?
-                    .id
-                    .clone();
-
-                self.cursor = Some(start_dialogue_fragment_id);
-            }
-            Ok(Model::Dialogue { .. }) => {
-                let start_dialogue_fragment_id = self
-                    .file
-                    .get_first_dialogue_fragment_of_dialogue(self.get_current_model().unwrap())?;
-                self.cursor = Some(start_dialogue_fragment_id);
+    #[test]
+    fn loads_files_in_the_background() {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+
+        struct ThreadWaker(std::thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
             }
-            Ok(_) => {}
-            Err(error) => Err(error)?,
         }
 
-        Ok(())
+        let reader = std::fs::File::open("./fixtures/example.json").unwrap();
+        let mut loading = Box::pin(File::from_reader_async(reader));
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut context = Context::from_waker(&waker);
+
+        let file = loop {
+            match loading.as_mut().poll(&mut context) {
+                Poll::Ready(file) => break file.unwrap(),
+                Poll::Pending => std::thread::park(),
+            }
+        };
+        assert_eq!(file.packages.len(), load_example().packages.len());
+
+        let mut failing = File::from_reader_async(&b"not json"[..]);
+        let result = loop {
+            match failing.try_take() {
+                Some(result) => break result,
+                None => std::thread::yield_now(),
+            }
+        };
+        assert!(matches!(result, Err(Error::FailedToParseFile(_))));
+
+        assert!(matches!(
+            File::from_reader(&b"{\"a\":1}"[..]),
+            Err(Error::FailedToParseFile(_))
+        ));
+        assert!(matches!(
+            File::try_from_buffer(b"[]"),
+            Err(Error::FailedToParseFile(_))
+        ));
     }
 
-    pub fn get_current_model(&self) -> Result<&Model, Error> {
-        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
+    #[test]
+    fn loads_files_from_a_path() {
+        let file = File::from_path("./fixtures/example.json").unwrap();
+        let example = load_example();
 
-        Ok(self
-            .file
-            .get_default_package()
+        assert_eq!(
+            file.get_default_package().models.len(),
+            example.get_default_package().models.len()
+        );
+        assert!(matches!(
+            File::from_path("./fixtures/missing.json"),
+            Err(Error::FailedToReadFile)
+        ));
+
+        let not_an_export = std::env::temp_dir().join("articy_not_an_export.json");
+        std::fs::write(&not_an_export, r#"{"a":1}"#).unwrap();
+        assert!(matches!(
+            File::from_path(&not_an_export),
+            Err(Error::FailedToParseFile(_))
+        ));
+        let _ = std::fs::remove_file(not_an_export);
+    }
+
+    #[test]
+    fn hashes_the_content_of_files() {
+        let mut file = load_example();
+        let hash = file.content_hash();
+
+        assert_eq!(hash, load_example().content_hash());
+
+        if let Some(Model::DialogueFragment { position, .. }) = file.packages[0]
             .models
-            .iter()
-            .find(|model| model.id() == *cursor)
-            .ok_or(Error::NoModel)?)
+            .iter_mut()
+            .find(|model| model.type_name() == "DialogueFragment")
+        {
+            position.x += 10.0;
+        }
+        assert_eq!(file.content_hash(), hash);
+
+        if let Some(Model::DialogueFragment { text, .. }) = file.packages[0]
+            .models
+            .iter_mut()
+            .find(|model| model.type_name() == "DialogueFragment")
+        {
+            text.push('!');
+        }
+        assert_ne!(file.content_hash(), hash);
     }
 
-    pub fn get_model(&self, id: Id) -> Result<&Model, Error> {
-        Ok(self
-            .file
-            .get_default_package()
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn restores_snapshots_of_the_same_content() {
+        use persistence::{ContentMismatch, PersistenceKey};
+
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        interpreter
+            .set_state("game.gold", StateValue::Int(3))
+            .unwrap();
+        let snapshot = interpreter.snapshot(PersistenceKey::TechnicalName);
+
+        let mut restored = Interpreter::new(Arc::new(load_example()));
+        restored.restore(&snapshot, ContentMismatch::Fail).unwrap();
+        assert_eq!(restored.cursor, interpreter.cursor);
+        assert_eq!(restored.get_state("game.gold"), Some(&StateValue::Int(3)));
+
+        let mut snapshot = snapshot;
+        snapshot.content_hash ^= 1;
+        assert!(matches!(
+            restored.restore(&snapshot, ContentMismatch::Fail),
+            Err(Error::ContentHashMismatch { .. })
+        ));
+        assert!(restored.restore(&snapshot, ContentMismatch::Ignore).is_ok());
+
+        assert!(restored.restore(&snapshot, ContentMismatch::Warn).is_ok());
+        assert!(matches!(
+            restored.recent_events().last().map(|event| &event.kind),
+            Some(EventKind::ContentMismatch { expected, .. }) if *expected == snapshot.content_hash
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "interpreter")]
+    fn restores_snapshots_in_place_of_a_later_state() {
+        use persistence::{ContentMismatch, PersistenceKey};
+
+        let dialogue = Id("0x0100000000000200".into());
+        let sword_line = Id("0x0100000000000303".into());
+
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(dialogue.clone()).unwrap();
+        interpreter.advance().unwrap();
+        let snapshot = interpreter.snapshot(PersistenceKey::TechnicalName);
+
+        interpreter.choose(sword_line.clone()).unwrap();
+        interpreter
+            .set_state("game.gold", StateValue::Int(7))
+            .unwrap();
+        interpreter
+            .set_state("game.unsaved", StateValue::Boolean(true))
+            .unwrap();
+        while !matches!(interpreter.advance(), Ok(Outcome::EndOfDialogue { .. })) {}
+        assert!(interpreter.finished.contains(&dialogue));
+
+        interpreter
+            .restore(&snapshot, ContentMismatch::Fail)
+            .unwrap();
+        assert_eq!(interpreter.cursor, snapshot.cursor);
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(10))
+        );
+        assert_eq!(interpreter.get_state("game.unsaved"), None);
+        assert!(!interpreter.visited.contains(&sword_line));
+        assert!(interpreter.finished.is_empty());
+        assert_eq!(
+            interpreter.export_seen(PersistenceKey::TechnicalName),
+            snapshot.seen
+        );
+    }
+
+    #[test]
+    fn looks_models_up_per_package() {
+        let mut file = load_example();
+        let shared_id = Id("0x0100000000000401".into());
+        let mut shared = file.get_model(&shared_id).unwrap().clone();
+        if let Model::Entity { display_name, .. } = &mut shared {
+            *display_name = "Alice (DLC)".to_owned();
+        }
+        let mut dlc_only = file
+            .get_model(&Id("0x0100000000000402".into()))
+            .unwrap()
+            .clone();
+        if let Model::Entity { id, .. } = &mut dlc_only {
+            *id = Id("0x0100000000000999".into());
+        }
+
+        file.packages.insert(
+            0,
+            Package {
+                name: "DLC1".to_owned(),
+                description: String::new(),
+                is_default_package: false,
+                models: vec![shared, dlc_only],
+            },
+        );
+
+        let display_name = |model: Option<&Model>| model.and_then(Model::display_name);
+        assert_eq!(
+            display_name(file.get_model(&shared_id)).as_deref(),
+            Some("Alice")
+        );
+        assert_eq!(
+            display_name(file.in_package("DLC1").get_model(&shared_id)).as_deref(),
+            Some("Alice (DLC)")
+        );
+        assert!(file.get_model(&Id("0x0100000000000999".into())).is_some());
+        assert!(file.in_package("DLC2").get_model(&shared_id).is_none());
+        assert_eq!(
+            file.in_package("DLC1")
+                .get_models_of_type(Type::Entity)
+                .len(),
+            2
+        );
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn advances_from_nodes_of_other_packages() {
+        let mut file = load_example();
+        let line = Id("0x0100000000000301".into());
+        let position = file.packages[0]
             .models
             .iter()
-            .find(|model| model.id() == id)
-            .ok_or(Error::NoModel)?)
+            .position(|model| model.id() == line)
+            .unwrap();
+        let model = file.packages[0].models.remove(position);
+        file.packages.push(Package {
+            name: "DLC1".to_owned(),
+            description: String::new(),
+            is_default_package: false,
+            models: vec![model],
+        });
+
+        let mut interpreter = Interpreter::new(Arc::new(file));
+        interpreter.jump_to(line, false).unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(choices)) if choices.len() == 1
+        ));
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn groups_choices_under_their_hub() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.set_text_override(
+            Id("0x0100000000000303".into()),
+            TextField::MenuText,
+            "Grab the sword".into(),
+        );
+
+        let group = interpreter.get_choice_group().unwrap();
+        let hub = group.hub.unwrap();
+        assert_eq!(hub.id, &Id("0x0100000000000302".into()));
+        assert_eq!(
+            group.choices.len(),
+            interpreter
+                .get_available_connections_at_cursor()
+                .unwrap()
+                .len()
+        );
+
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        assert!(interpreter.get_choice_group().unwrap().hub.is_none());
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn stops_when_the_budget_is_exceeded() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.budget.max_technical_nodes = Some(0);
+        interpreter
+            .jump_to(Id("0x0100000000000306".into()), false)
+            .unwrap();
+
+        assert!(matches!(
+            interpreter.advance(),
+            Err(Error::BudgetExceeded(id)) if id == Id("0x0100000000000306".into())
+        ));
+
+        interpreter.budget = Budget {
+            max_evaluations: Some(1),
+            max_technical_nodes: None,
+        };
+        assert!(interpreter.advance().is_ok());
+
+        // Pin conditions evaluated to offer the choices of a Hub count as well
+        let gold_line = Id("0x0100000000000304".into());
+        interpreter.budget.max_evaluations = Some(0);
+        interpreter
+            .jump_to(Id("0x0100000000000302".into()), false)
+            .unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Err(Error::BudgetExceeded(id)) if id == gold_line
+        ));
+        assert!(matches!(
+            interpreter.choose(Id("0x0100000000000303".into())),
+            Err(Error::BudgetExceeded(id)) if id == gold_line
+        ));
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn evaluates_single_pins() {
+        let file = Arc::new(load_example());
+        let mut interpreter = Interpreter::new(file.clone());
+        let pin = file.get_pin(&Id("0x0300000000000304".into())).unwrap();
+
+        assert_eq!(pin.owner, Id("0x0100000000000304".into()));
+        assert!(!interpreter.evaluate_pin(pin).unwrap());
+
+        interpreter
+            .set_state("game.has_gold", StateValue::Boolean(true))
+            .unwrap();
+        assert!(interpreter.evaluate_pin(pin).unwrap());
+        assert!(file.get_pin(&Id("0x0300000000000999".into())).is_none());
     }
 
-    pub fn get_available_connections_at_cursor(&self) -> Result<Vec<&Model>, Error> {
-        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
-        self.get_available_connections(cursor)
+    #[test]
+    fn resolves_template_references() {
+        let file = load_example();
+        let quest = file.get_model(&Id("0x0100000000000500".into())).unwrap();
+
+        let references = file.resolve_references(quest);
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0].0, "giver");
+        assert_eq!(references[0].1.id(), Id("0x0100000000000401".into()));
+
+        let alice = file.get_model(&Id("0x0100000000000401".into())).unwrap();
+        assert!(file.resolve_references(alice).is_empty());
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn tracks_flow_coverage() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.coverage = Some(coverage::Coverage::default());
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        interpreter.advance().unwrap();
+
+        let file = interpreter.file.clone();
+        let mut coverage = interpreter.coverage.take().unwrap();
+        let dialogue = Id("0x0100000000000200".into());
+
+        let report = coverage.dialogue_coverage(&file, &dialogue);
+        assert_eq!(report.nodes_seen, 2);
+        assert_eq!(report.connections_seen, 1);
+        assert!(!report
+            .never_seen_lines
+            .contains(&Id("0x0100000000000301".into())));
+        assert!(report
+            .never_seen_lines
+            .contains(&Id("0x0100000000000303".into())));
+
+        let mut other = coverage::Coverage::default();
+        other.record_node(&Id("0x0100000000000303".into()));
+        coverage.merge(&other);
+
+        let merged = coverage.dialogue_coverage(&file, &dialogue);
+        assert_eq!(merged.nodes_seen, 3);
+        assert!(merged.node_percentage() > report.node_percentage());
+        assert_eq!(coverage.report(&file).len(), 1);
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn auto_advances_through_filtered_speakers() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.auto_advance_speakers([Id("0x0100000000000402".into())].into_iter().collect());
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+
+        assert!(interpreter
+            .visited
+            .contains(&Id("0x0100000000000303".into())));
+        assert_eq!(interpreter.cursor, Some(Id("0x0100000000000305".into())));
+
+        interpreter.clear_auto_advance();
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        assert_eq!(interpreter.cursor, Some(Id("0x0100000000000303".into())));
     }
-    pub fn get_available_connections(&self, model_id: &Id) -> Result<Vec<&Model>, Error> {
-        let model = self.get_model(model_id.clone())?;
 
-        Ok(model
-            .output_pins()
-            .expect("Model to have output pins")
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn exposes_connection_labels_and_colors() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.set_text_override(
+            Id("0x0100000000000303".into()),
+            TextField::MenuText,
+            "Grab the sword".into(),
+        );
+
+        let group = interpreter.get_choice_group().unwrap();
+        let (connection, _) = group
+            .connections
+            .iter()
+            .zip(&group.choices)
+            .find(|(_, choice)| choice.id() == Id("0x0100000000000303".into()))
+            .unwrap();
+
+        assert_eq!(connection.label, "Take it");
+        assert_eq!(
+            connection.color.as_ref().map(Color::to_hex).as_deref(),
+            Some("#FF8000")
+        );
+        assert!(group
+            .connections
             .iter()
-            .filter_map(|pin| {
-                Some(
-                    pin.connections
-                        .iter()
-                        .filter_map(|connection| {
-                            let target_model = self
-                                .file
-                                .get_default_package()
-                                .models
-                                .iter()
-                                .find(|model| model.id() == connection.target)?;
-
-                            let target_pin = target_model
-                                .input_pins()
-                                .expect("Target model to have input pins")
-                                .iter()
-                                .find(|pin| pin.id == connection.target_pin)?;
-
-                            match target_pin.text.as_ref() {
-                                "" => Some(target_model),
-                                expression => {
-                                    match eval_boolean_with_context(expression, &self.state) {
-                                        Ok(outcome) => match outcome {
-                                            true => Some(target_model),
-                                            false => None,
-                                        },
-                                        Err(_) => None,
-                                    }
-                                }
-                            }
-                        })
-                        .collect::<Vec<&Model>>(),
-                )
+            .filter(|connection| connection.label.is_empty())
+            .all(|connection| connection.color.is_none()));
+    }
+
+    #[test]
+    fn looks_models_up_by_short_id() {
+        let file = load_example();
+        let greeting = file.get_model(&Id("0x0100000000000301".into())).unwrap();
+        let short_id = greeting.short_id().unwrap();
+
+        assert_eq!(
+            file.get_model_by_short_id(short_id).map(Model::id),
+            Some(greeting.id())
+        );
+        assert!(file.get_model_by_short_id(u32::MAX).is_none());
+    }
+
+    #[test]
+    fn queries_files_from_several_threads() {
+        let handle = query::FileQuery::from(load_example());
+
+        let threads = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                std::thread::spawn(move || {
+                    handle
+                        .get_model(&Id("0x0100000000000301".into()))
+                        .and_then(Model::technical_name)
+                })
             })
-            .flatten()
-            .collect::<Vec<&Model>>())
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap().as_deref(), Some("DFr_301"));
+        }
+        assert_eq!(
+            handle.search("sword").len(),
+            load_example().search("sword").len()
+        );
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn keeps_the_most_recent_events() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.event_capacity = 2;
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+
+        let events = interpreter
+            .recent_events()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                EventKind::ChoiceMade(Id("0x0100000000000303".into())),
+                EventKind::NodeEntered(Id("0x0100000000000303".into())),
+            ]
+        );
     }
 
-    pub fn choose(&mut self, id: Id) -> Result<Outcome, Error> {
-        match self
-            .get_available_connections_at_cursor()
-            .ok()
-            .ok_or(Error::NoOutputConnected)?
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn labels_choices_through_the_fallback_chain() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.start(Id("0x0100000000000302".into())).unwrap();
+        interpreter.set_text_override(
+            Id("0x0100000000000303".into()),
+            TextField::MenuText,
+            "Grab the sword".into(),
+        );
+
+        let group = interpreter.get_choice_group().unwrap();
+        let options = group.options();
+        let take_sword = options
             .iter()
-            .filter_map(|choice| {
-                let expression = &choice.input_pins()?.first()?.text;
-
-                match (
-                    expression.is_empty(),
-                    eval_boolean_with_context(expression, &self.state),
-                ) {
-                    (true, _) | (false, Ok(true)) => Some(choice),
-                    _ => None,
-                }
+            .find(|option| option.model.id() == Id("0x0100000000000303".into()))
+            .unwrap();
+
+        assert_eq!(take_sword.label(), "Take the sword");
+        assert_eq!(interpreter.choice_label(take_sword), "Grab the sword");
+        assert_eq!(
+            take_sword.label_with(&[TextField::DisplayName, TextField::Text]),
+            take_sword.model.text().unwrap()
+        );
+    }
+
+    #[test]
+    fn finds_zones_by_template() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct SpawnArea {
+            max_enemies: u32,
+            enemy: String,
+        }
+
+        let file = load_example();
+        let zones = file.get_zones_with_template("SpawnArea");
+
+        assert_eq!(zones.len(), 1);
+        assert_eq!(
+            zones[0].template_as::<SpawnArea>("SpawnArea"),
+            Some(SpawnArea {
+                max_enemies: 3,
+                enemy: "Goblin".to_owned()
             })
-            .find(|choice| choice.id() == id)
-        {
-            Some(choice) => {
-                self.cursor = Some(choice.id());
-                let model = self
-                    .get_current_model()
-                    .expect("model to be succesfully selected after choice");
+        );
+        assert!(file.get_spots_with_template("SpawnArea").is_empty());
+        assert!(file.get_zones_with_template("QuestNode").is_empty());
+    }
+
+    #[test]
+    fn collects_load_warnings() {
+        assert_eq!(load_example().load_warnings(), &[]);
+
+        let bytes = std::fs::read("./fixtures/example.json").expect("to be able to read the file");
+        let mut json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        json["Settings"]["set_Localization"] = "Maybe".into();
+        json["Settings"]["set_IncludedNodes"] = "Settings, Locations".into();
+
+        let models = json["Packages"][0]["Models"].as_array_mut().unwrap();
+        let hub = models
+            .iter_mut()
+            .find(|model| model["Type"] == "Hub")
+            .unwrap();
+        hub["Properties"]["ShortId"] = "not a number".into();
+        let pin = &mut hub["Properties"]["OutputPins"][0];
+        pin.as_object_mut().unwrap().remove("Connections");
+        let pin_id = pin["Id"].as_str().unwrap().to_owned();
+
+        let file = File::from_buffer(&serde_json::to_vec(&json).unwrap());
+        let warnings = file.load_warnings();
+
+        assert!(warnings.contains(&warnings::LoadWarning::InvalidBool("Maybe".into())));
+        assert!(warnings.contains(&warnings::LoadWarning::UnknownNodeType("Locations".into())));
+        assert!(
+            warnings.contains(&warnings::LoadWarning::MissingConnections {
+                pin: Id(pin_id.into())
+            })
+        );
+        assert!(warnings.iter().any(|warning| matches!(
+            warning,
+            warnings::LoadWarning::FellBackToCustom { id, kind, .. }
+                if id == &Id("0x0100000000000302".into()) && kind == "Hub"
+        )));
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn follows_the_first_open_branch_of_instructions() {
+        let instruction = Id("0x0100000000000305".into());
+        let mut file = load_example();
+        let condition_line = Id("0x0100000000000304".into());
 
-                Ok(Outcome::Advanced(&model))
+        for model in &mut file.packages[0].models {
+            if let Model::Instruction {
+                id, output_pins, ..
+            } = model
+            {
+                if *id == instruction {
+                    let existing = output_pins[0].connections[0].clone();
+                    output_pins[0].connections = vec![
+                        Connection {
+                            label: String::new(),
+                            target_pin: Id("0x0300000000000304".into()),
+                            target: condition_line.clone(),
+                            color: None,
+                            extra: Default::default(),
+                        },
+                        existing,
+                    ];
+                }
             }
-            None => self.advance(),
         }
+
+        let issues = validation::lint_flow(&file);
+        assert!(issues.iter().any(|issue| issue.id == instruction
+            && issue.kind == validation::IssueKind::BranchingInstruction));
+
+        let mut interpreter = Interpreter::new(Arc::new(file));
+        interpreter.jump_to(instruction.clone(), false).unwrap();
+        interpreter.advance().unwrap();
+
+        // The line behind the first connection requires gold, so the second connection is followed
+        assert_ne!(interpreter.cursor, Some(condition_line));
+        assert!(interpreter.recent_events().iter().any(|event| matches!(
+            &event.kind,
+            EventKind::BranchesDropped { node, followed: Some(_) } if *node == instruction
+        )));
     }
 
-    pub fn advance(&mut self) -> Result<Outcome, Error> {
-        let cursor = self.cursor.as_ref().ok_or(Error::NoCursor)?;
-        let model = self
-            .file
-            .get_default_package()
-            .models
-            .iter()
-            .find(|model| model.id() == *cursor)
-            .ok_or(Error::NoModel)?;
-
-        match model {
-            Model::Dialogue { .. } => Ok(Outcome::EndOfDialogue),
-            Model::DialogueFragment { output_pins, .. } => {
-                let connections = self
-                    .get_available_connections_at_cursor()
-                    .ok()
-                    .ok_or(Error::NoOutputConnected)?
-                    .len();
-
-                if connections > 1 {
-                    return Ok(Outcome::WaitingForChoice(
-                        self.get_available_connections_at_cursor()
-                            .ok()
-                            .ok_or(Error::NoOutputConnected)?,
-                    ));
-                } else {
-                    self.cursor = Some(
-                        output_pins
-                            .first()
-                            .ok_or(Error::NoOutputConnected)?
-                            .connections
-                            .first()
-                            .ok_or(Error::NoOutputConnected)?
-                            .target
-                            .clone(),
-                    );
-                }
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn records_transcripts() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.transcript = Some(transcript::Transcript::default());
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        interpreter.advance().unwrap();
+        interpreter.advance().unwrap();
 
-                self.post_advance()
-            }
-            // Serves as a point for choices
-            Model::Hub { .. } => {
-                let choices = self
-                    .get_available_connections_at_cursor()
-                    .ok()
-                    .ok_or(Error::NoOutputConnected)?;
-
-                Ok(Outcome::WaitingForChoice(choices))
-            }
-            // TODO: Implement FlowFragment for triggering things in-game?
-            Model::FlowFragment { .. } => {
-                todo!("FlowFragment still needs to be implemented in articy-rs")
-            }
+        let transcript = interpreter.transcript.take().unwrap();
+        assert_eq!(
+            transcript.to_text(),
+            "Alice: Hello there, traveller.\n\
+             > Take the sword\n\
+             Bob: I'll take the sword.\n  \
+             game.sword_taken: unset -> true\n\
+             Alice: A fine choice.\n"
+        );
+        assert!(transcript
+            .to_markdown()
+            .starts_with("**Alice:** Hello there, traveller.  \n> *Take the sword*\n"));
 
-            Model::Condition {
-                expression,
-                output_pins,
-                ..
-            } => {
-                let result = match eval_boolean_with_context(&expression, &self.state) {
-                    Ok(result) => result,
-                    _ => false,
-                };
-
-                println!("[Condition] Input ({expression}); Outcome: {result}");
-
-                self.cursor = Some(if result {
-                    output_pins
-                        .first()
-                        .ok_or(Error::NoOutputConnected)?
-                        .connections
-                        .first()
-                        .ok_or(Error::NoOutputConnected)?
-                        .target
-                        .clone()
-                } else {
-                    output_pins
-                        .last()
-                        .ok_or(Error::NoOutputConnected)?
-                        .connections
-                        .first()
-                        .ok_or(Error::NoOutputConnected)?
-                        .target
-                        .clone()
-                });
-
-                self.post_advance()
-            }
+        let json = serde_json::to_string(&transcript).unwrap();
+        let parsed: transcript::Transcript = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, transcript);
+    }
 
-            Model::Instruction {
-                expression,
-                output_pins,
-                ..
-            } => {
-                let result = eval_with_context_mut(&expression, &mut self.state);
-
-                println!("[Instruction] Input ({expression}); Outcome: {result:#?}");
-
-                self.cursor = Some(
-                    output_pins
-                        .first()
-                        .ok_or(Error::NoOutputConnected)?
-                        .connections
-                        .first()
-                        .ok_or(Error::NoOutputConnected)?
-                        .target
-                        .clone(),
-                );
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn restores_dialogue_local_variables() {
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+        interpreter.local_namespaces = vec!["quest".to_owned()];
+        interpreter
+            .set_state("quest.stage", StateValue::Int(0))
+            .unwrap();
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
 
-                self.post_advance()
-            }
+        interpreter
+            .set_state("quest.stage", StateValue::Int(5))
+            .unwrap();
+        interpreter
+            .set_state("game.gold", StateValue::Int(5))
+            .unwrap();
+        interpreter.advance().unwrap();
+        interpreter.choose(Id("0x0100000000000303".into())).unwrap();
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(5))
+        );
+        let exported = interpreter.export_variables(None);
+        assert_eq!(exported.get("quest.stage"), Some(&StateValue::Int(0)));
+        assert_eq!(exported.get("game.gold"), Some(&StateValue::Int(5)));
 
-            kind => unimplemented!("Forgot to implement type {kind:?} for Interpreter::advance"),
+        loop {
+            if let Outcome::EndOfDialogue { .. } = interpreter.advance().unwrap() {
+                break;
+            }
         }
+
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(0))
+        );
+        assert_eq!(
+            interpreter.get_state("game.gold"),
+            Some(&StateValue::Int(5))
+        );
+    }
+
+    #[test]
+    fn lists_expression_symbols() {
+        let file = load_example();
+        let symbols = file.expression_symbols();
+
+        let has_gold = symbols
+            .iter()
+            .find(|symbol| symbol.name == "game.has_gold")
+            .unwrap();
+        assert!(matches!(
+            &has_gold.kind,
+            symbols::SymbolKind::Variable { value_type, .. } if value_type == "Boolean"
+        ));
+        assert!(symbols.iter().any(|symbol| symbol.name == "min"
+            && matches!(symbol.kind, symbols::SymbolKind::BuiltIn { .. })));
+    }
+
+    #[test]
+    fn lists_startable_nodes() {
+        let file = load_example();
+        let nodes = file.list_startable_nodes();
+
+        let hub = nodes
+            .iter()
+            .find(|node| node.id == Id("0x0100000000000302".into()))
+            .unwrap();
+        assert_eq!(hub.path.last().map(String::as_str), Some("Intro"));
+        assert!(nodes
+            .iter()
+            .any(|node| node.id == Id("0x0100000000000200".into())));
+        assert!(!nodes
+            .iter()
+            .any(|node| node.id == Id("0x0100000000000301".into())));
+
+        let file = load_cyclic_example();
+        assert!(!file.list_startable_nodes().is_empty());
     }
 
-    pub fn post_advance(&mut self) -> Result<Outcome, Error> {
-        Ok(match self.get_current_model().ok().ok_or(Error::NoModel)? {
-            Model::Dialogue { .. } => Outcome::EndOfDialogue,
-            Model::Hub { .. } => {
-                let choices = self
-                    .get_available_connections_at_cursor()
-                    .ok()
-                    .ok_or(Error::NoOutputConnected)?;
+    #[test]
+    fn rejects_cyclic_hierarchy_paths() {
+        let file = load_cyclic_example();
+        let line = file.get_model(&Id("0x0100000000000301".into())).unwrap();
+
+        assert!(matches!(
+            file.get_hierarchy_path_from_model(line),
+            Err(Error::NoHierarchy)
+        ));
 
-                Outcome::WaitingForChoice(choices)
+        // Without an entry connection the first line is looked up through the hierarchy
+        #[cfg(feature = "interpreter")]
+        {
+            let mut file = file;
+            for model in file
+                .packages
+                .iter_mut()
+                .flat_map(|package| &mut package.models)
+            {
+                if let Model::Dialogue { input_pins, .. } = model {
+                    input_pins[0].connections.clear();
+                }
             }
-            Model::Condition { .. } => return self.advance(),
-            _ => Outcome::Advanced(self.get_current_model().ok().ok_or(Error::NoModel)?),
-        })
+
+            assert!(matches!(
+                Interpreter::new(Arc::new(file)).start(Id("0x0100000000000200".into())),
+                Err(Error::NoHierarchy)
+            ));
+        }
     }
 
-    /// Goes through all of the nodes until meeting some that force it to stop,
-    /// will not tell you what outcome though since that would require looping with a &mut self 😓
-    pub fn exhaust_maximally(&mut self) -> Result<(), Error> {
-        loop {
-            match self.advance()? {
-                // TODO: If there are any state changes applied due to specific node types, be sure to apply them here as well?
-                Outcome::Advanced(..) => continue,
-                _ => break Ok(()),
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn reports_unsupported_nodes_instead_of_panicking() {
+        let alice = Id("0x0100000000000401".into());
+        let mut interpreter = Interpreter::new(Arc::new(load_example()));
+
+        assert!(matches!(
+            interpreter.get_available_connections(&alice),
+            Err(Error::MissingPins { id }) if id == alice
+        ));
+
+        interpreter.cursor = Some(alice.clone());
+        assert!(matches!(
+            interpreter.advance(),
+            Err(Error::UnsupportedNodeType { id, kind }) if id == alice && kind == "Entity"
+        ));
+
+        // A line wired into an Entity by mistake
+        let mut file = load_example();
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            if let Model::DialogueFragment {
+                id, output_pins, ..
+            } = model
+            {
+                if *id == Id("0x0100000000000301".into()) {
+                    output_pins[0].connections[0].target = alice.clone();
+                }
             }
         }
+
+        let mut interpreter = Interpreter::new(Arc::new(file));
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Err(Error::MissingPins { id }) if id == alice
+        ));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use types::*;
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn advances_without_a_default_package() {
+        let mut file = load_example();
+        file.packages[0].is_default_package = false;
+
+        let mut interpreter = Interpreter::new(Arc::new(file));
+        interpreter.start(Id("0x0100000000000200".into())).unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(_))
+        ));
+    }
 
+    #[cfg(feature = "interpreter")]
     #[test]
-    fn parses_example_project() {
-        let json =
-            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+    fn evaluates_branching_lines_once() {
+        let mut interpreter = Interpreter::new(Arc::new(load_branching_example()));
+        interpreter
+            .set_state("game.has_gold", StateValue::Boolean(true))
+            .unwrap();
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
 
-        let _articy_file: File =
-            serde_json::from_str(&json).expect("to be able to parse articy data");
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(choices)) if choices.len() == 2
+        ));
+        assert_eq!(interpreter.metrics().conditions_evaluated, 1);
     }
 
+    #[cfg(feature = "interpreter")]
     #[test]
-    fn get_list_of_objects_definitions() {
-        let json =
-            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+    fn follows_the_open_connection_of_a_line() {
+        let mut interpreter = Interpreter::new(Arc::new(load_branching_example()));
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
 
-        let file: File = serde_json::from_str(&json).expect("to be able to parse articy data");
+        let sword_line = Id("0x0100000000000303".into());
+        let available = interpreter.get_available_connections_at_cursor().unwrap();
+        assert_eq!(
+            available
+                .iter()
+                .map(|model| model.id())
+                .collect::<Vec<Id>>(),
+            vec![sword_line.clone()]
+        );
 
-        for object in &file.object_definitions {
-            println!("{:?}", object.kind)
+        interpreter.advance().unwrap();
+        assert_eq!(interpreter.cursor, Some(sword_line));
+    }
+
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn chooses_the_exit_of_a_branching_line() {
+        let mut file = load_branching_example();
+        let dialogue = Id("0x0100000000000200".into());
+        let exit_pin = Id("0x0200000000000202".into());
+
+        for model in file
+            .packages
+            .iter_mut()
+            .flat_map(|package| &mut package.models)
+        {
+            match model {
+                Model::DialogueFragment {
+                    id, output_pins, ..
+                } if *id == Id("0x0100000000000301".into()) => {
+                    let mut to_exit = output_pins[0].connections[0].clone();
+                    to_exit.target = dialogue.clone();
+                    to_exit.target_pin = exit_pin.clone();
+
+                    output_pins[0].connections.push(to_exit);
+                }
+                // The text of an exit pin is an instruction, it mustn't be evaluated as a condition
+                Model::Dialogue { output_pins, .. } => {
+                    output_pins[1].text = "quest.stage = 2".into();
+                }
+                _ => {}
+            }
         }
+
+        let mut interpreter = Interpreter::new(Arc::new(file));
+        interpreter.error_policy = ErrorPolicy::Fail;
+        interpreter
+            .set_state("game.has_gold", StateValue::Boolean(false))
+            .unwrap();
+        interpreter.start(Id("0x0100000000000301".into())).unwrap();
+        assert!(matches!(
+            interpreter.advance(),
+            Ok(Outcome::WaitingForChoice(choices)) if choices.len() == 2
+        ));
+
+        let outcome = interpreter.choose(dialogue.clone()).unwrap();
+        assert!(matches!(
+            outcome,
+            Outcome::EndOfDialogue { dialogue: ended, exit_pin: Some(pin) }
+                if ended == dialogue && pin == exit_pin
+        ));
+        assert_eq!(
+            interpreter.get_state("quest.stage"),
+            Some(&StateValue::Int(2))
+        );
     }
 
+    #[cfg(feature = "interpreter")]
     #[test]
-    fn get_list_of_models() {
-        let json =
-            std::fs::read_to_string("./craftcraft.json").expect("to be able to read the file");
+    fn evaluates_every_builtin_symbol() {
+        for symbol in load_example().expression_symbols() {
+            if let symbols::SymbolKind::BuiltIn { .. } = symbol.kind {
+                let result = evalexpr::eval(&format!("{}(1)", symbol.name));
 
-        let file: File = serde_json::from_str(&json).expect("to be able to parse articy data");
+                assert!(
+                    !matches!(
+                        result,
+                        Err(evalexpr::EvalexprError::FunctionIdentifierNotFound(_))
+                    ),
+                    "{} isn't a function of the evaluator",
+                    symbol.name
+                );
+            }
+        }
+    }
 
-        let models = file.get_models_of_type("Dialogue");
+    #[cfg(feature = "interpreter")]
+    #[test]
+    fn queries_the_file_of_an_interpreter() {
+        let interpreter = Interpreter::new(Arc::new(load_example()));
+        let handle = query::FileQuery::new(interpreter.file.clone());
+        let line = Id("0x0100000000000301".into());
 
-        println!("models: {models:#?}");
+        assert_eq!(Arc::strong_count(&interpreter.file), 2);
+        assert!(std::ptr::eq(
+            handle.get_model(&line).unwrap(),
+            interpreter.get_model(line).unwrap()
+        ));
     }
 }
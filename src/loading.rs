@@ -0,0 +1,96 @@
+use std::future::Future;
+use std::io::Read;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::types::{Error, File};
+
+impl File {
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut bytes = vec![];
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(|_| Error::FailedToReadFile)?;
+
+        File::try_from_buffer(&bytes)
+    }
+
+    /// Reads and parses the export on a separate thread, the returned future resolves once it's done.
+    /// It doesn't depend on a specific runtime so it can be awaited from any executor, or polled once a frame.
+    pub fn from_reader_async<R: Read + Send + 'static>(reader: R) -> Loading {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let sender = Sender(shared.clone());
+
+        thread::spawn(move || {
+            let file = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                File::from_reader(reader)
+            }))
+            .unwrap_or_else(|_| {
+                Err(Error::FailedToParseFile(
+                    "the loader panicked while parsing".to_owned(),
+                ))
+            });
+
+            sender.send(file);
+        });
+
+        Loading(shared)
+    }
+}
+
+/// A `File` being loaded in the background, see `File::from_reader_async`
+pub struct Loading(Arc<Mutex<Shared>>);
+
+#[derive(Default)]
+struct Shared {
+    file: Option<Result<File, Error>>,
+    waker: Option<Waker>,
+}
+
+struct Sender(Arc<Mutex<Shared>>);
+
+impl Sender {
+    fn send(self, file: Result<File, Error>) {
+        let mut shared = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        shared.file = Some(file);
+
+        if let Some(waker) = shared.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl Loading {
+    /// Takes the file if it's done loading, without blocking
+    pub fn try_take(&mut self) -> Option<Result<File, Error>> {
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .file
+            .take()
+    }
+}
+
+impl Future for Loading {
+    type Output = Result<File, Error>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        match shared.file.take() {
+            Some(file) => Poll::Ready(file),
+            None => {
+                shared.waker = Some(context.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "interpreter")]
+use evalexpr::HashMapContext;
+
+use crate::types::{Error, File, Id, Model};
+
+/// Runtime localization for one locale, bound to the interpreter with `Interpreter::bind_localizer`.
+///
+/// `message` is the technical name of the node (or its id when a message can't be found by name), `attribute`
+/// is `None` for the text and the field name (e.g `menu_text`) otherwise, and `variables` is the interpreter state
+/// to look the variables used as placeables up in by their full name (e.g `game.gold`).
+#[cfg(feature = "interpreter")]
+pub trait Localizer {
+    fn format(
+        &self,
+        message: &str,
+        attribute: Option<&str>,
+        variables: &HashMapContext,
+    ) -> Option<String>;
+}
+
+/// The text-bearing fields of models that end up in a translation table
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextField {
+    Text,
+    MenuText,
+    StageDirections,
+    DisplayName,
+}
+
+impl TextField {
+    pub const ALL: [TextField; 4] = [
+        TextField::Text,
+        TextField::MenuText,
+        TextField::StageDirections,
+        TextField::DisplayName,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextField::Text => "text",
+            TextField::MenuText => "menu_text",
+            TextField::StageDirections => "stage_directions",
+            TextField::DisplayName => "display_name",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TextField> {
+        TextField::ALL
+            .into_iter()
+            .find(|field| field.name() == name)
+    }
+}
+
+impl Model {
+    pub fn text_field(&self, field: TextField) -> Option<String> {
+        match (field, self) {
+            (TextField::Text, _) => self.text(),
+            (TextField::DisplayName, _) => self.display_name(),
+            (TextField::MenuText, Model::DialogueFragment { menu_text, .. }) => {
+                Some(menu_text.to_owned())
+            }
+            (
+                TextField::StageDirections,
+                Model::DialogueFragment {
+                    stage_directions, ..
+                },
+            ) => Some(stage_directions.to_owned()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringEntry {
+    pub id: Id,
+    pub field: TextField,
+    pub source: String,
+    pub translation: String,
+}
+
+impl StringEntry {
+    /// The key used in the exported tables, e.g `0x0100000000000301.menu_text`
+    pub fn key(&self) -> String {
+        format!("{}.{}", self.id.0, self.field.name())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StringTable {
+    pub entries: Vec<StringEntry>,
+}
+
+impl StringTable {
+    /// Collects every non-empty text field of all models, translations start out empty
+    pub fn extract(file: &File) -> StringTable {
+        let entries = file
+            .get_models()
+            .into_iter()
+            .flat_map(|model| {
+                TextField::ALL.into_iter().filter_map(move |field| {
+                    let source = model.text_field(field)?;
+
+                    (!source.is_empty()).then(|| StringEntry {
+                        id: model.id(),
+                        field,
+                        source,
+                        translation: "".to_owned(),
+                    })
+                })
+            })
+            .collect();
+
+        StringTable { entries }
+    }
+
+    /// Entries with a non-empty translation, keyed by node and field
+    pub fn translations(&self) -> HashMap<(Id, TextField), String> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.translation.is_empty())
+            .map(|entry| ((entry.id.clone(), entry.field), entry.translation.clone()))
+            .collect()
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "key,source,translation\n".to_owned();
+
+        for entry in &self.entries {
+            let row = [entry.key(), entry.source.clone(), entry.translation.clone()]
+                .map(|cell| escape_csv(&cell));
+
+            csv.push_str(&row.join(","));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    pub fn from_csv(csv: &str) -> Result<StringTable, Error> {
+        let mut rows = parse_csv(csv)?.into_iter();
+        rows.next().ok_or(Error::FailedToParseStringTable)?;
+
+        let entries = rows
+            .filter(|row| row.iter().any(|cell| !cell.is_empty()))
+            .map(|row| match row.as_slice() {
+                [key, source, translation] => parse_entry(key, source, translation),
+                _ => Err(Error::FailedToParseStringTable),
+            })
+            .collect::<Result<Vec<StringEntry>, Error>>()?;
+
+        Ok(StringTable { entries })
+    }
+
+    /// Writes the table as XLIFF 1.2, with the keys as trans-unit ids
+    pub fn to_xliff(&self, source_language: &str, target_language: &str) -> String {
+        let mut xliff = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <xliff version=\"1.2\" xmlns=\"urn:oasis:names:tc:xliff:document:1.2\">\n  \
+             <file original=\"articy\" datatype=\"plaintext\" source-language=\"{}\" target-language=\"{}\">\n    \
+             <body>\n",
+            escape_xml(source_language),
+            escape_xml(target_language)
+        );
+
+        for entry in &self.entries {
+            xliff.push_str(&format!(
+                "      <trans-unit id=\"{}\">\n        <source>{}</source>\n        <target>{}</target>\n      </trans-unit>\n",
+                escape_xml(&entry.key()),
+                escape_xml(&entry.source),
+                escape_xml(&entry.translation)
+            ));
+        }
+
+        xliff.push_str("    </body>\n  </file>\n</xliff>\n");
+        xliff
+    }
+
+    /// Reads the trans-units of an XLIFF file, as written by `to_xliff` or translation tools keeping its structure
+    pub fn from_xliff(xliff: &str) -> Result<StringTable, Error> {
+        let mut entries = vec![];
+        let mut rest = xliff;
+
+        while let Some(start) = rest.find("<trans-unit") {
+            let end = rest[start..]
+                .find("</trans-unit>")
+                .ok_or(Error::FailedToParseStringTable)?
+                + start;
+            let unit = &rest[start..end];
+
+            let key = unit
+                .split_once("id=\"")
+                .and_then(|(_, key)| key.split_once('"'))
+                .ok_or(Error::FailedToParseStringTable)?
+                .0;
+            let source = xml_element(unit, "source").ok_or(Error::FailedToParseStringTable)?;
+            let translation = xml_element(unit, "target").unwrap_or_default();
+
+            entries.push(parse_entry(
+                &unescape_xml(key),
+                &unescape_xml(source),
+                &unescape_xml(translation),
+            )?);
+
+            rest = &rest[end..];
+        }
+
+        Ok(StringTable { entries })
+    }
+}
+
+fn parse_entry(key: &str, source: &str, translation: &str) -> Result<StringEntry, Error> {
+    let (id, field) = key
+        .rsplit_once('.')
+        .ok_or(Error::FailedToParseStringTable)?;
+
+    Ok(StringEntry {
+        id: Id(id.into()),
+        field: TextField::from_name(field).ok_or(Error::FailedToParseStringTable)?,
+        source: source.to_owned(),
+        translation: translation.to_owned(),
+    })
+}
+
+pub(crate) fn escape_csv(cell: &str) -> String {
+    if cell.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_owned()
+    }
+}
+
+fn parse_csv(csv: &str) -> Result<Vec<Vec<String>>, Error> {
+    let mut rows = vec![];
+    let mut row = vec![];
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut characters = csv.chars().peekable();
+
+    while let Some(character) = characters.next() {
+        match (character, quoted) {
+            ('"', true) if characters.peek() == Some(&'"') => {
+                cell.push('"');
+                characters.next();
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if cell.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut cell)),
+            ('\r', false) => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            (character, _) => cell.push(character),
+        }
+    }
+
+    if quoted {
+        return Err(Error::FailedToParseStringTable);
+    }
+
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+
+    Ok(rows)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn xml_element<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = xml.find(&format!("<{name}"))?;
+    let content = open + xml[open..].find('>')? + 1;
+
+    if xml[..content].ends_with("/>") {
+        return Some("");
+    }
+
+    let close = content + xml[content..].find(&format!("</{name}>"))?;
+
+    Some(&xml[content..close])
+}
@@ -0,0 +1,32 @@
+use crate::types::{File, Model, Type};
+
+impl File {
+    /// Zones exported with the given template (e.g "SpawnArea"), read the template through `Model::template_as`.
+    /// Location objects aren't typed models yet, so these are `Model::Custom`s recognized through the
+    /// object definition of the template.
+    pub fn get_zones_with_template(&self, template: &str) -> Vec<&Model> {
+        self.get_templated_models_of_class(template, |class| matches!(class, Type::Zone))
+    }
+
+    /// Same as `get_zones_with_template`, for Spots
+    pub fn get_spots_with_template(&self, template: &str) -> Vec<&Model> {
+        self.get_templated_models_of_class(template, |class| matches!(class, Type::Spot))
+    }
+
+    fn get_templated_models_of_class(
+        &self,
+        template: &str,
+        is_class: impl Fn(&Type) -> bool,
+    ) -> Vec<&Model> {
+        let defined = self
+            .object_definitions
+            .iter()
+            .any(|object| object.kind.to_string() == template && is_class(&object.class));
+
+        if !defined {
+            return vec![];
+        }
+
+        self.get_models_of_type(template)
+    }
+}
@@ -0,0 +1,237 @@
+#[cfg(feature = "interpreter")]
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::localization::TextField;
+use crate::types::{File, Model, Pin};
+#[cfg(feature = "interpreter")]
+use crate::{
+    types::{Error, Id},
+    EventKind, Interpreter, Variables,
+};
+
+/// What visited/finished nodes are stored by, Articy's hex ids can change between exports while these don't
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PersistenceKey {
+    TechnicalName,
+    ExternalId,
+}
+
+impl PersistenceKey {
+    /// The key the model is stored by, `None` if the model doesn't have one
+    pub fn of(&self, model: &Model) -> Option<String> {
+        let key = match self {
+            PersistenceKey::TechnicalName => model.technical_name()?,
+            PersistenceKey::ExternalId => model.external_id().to_inner(),
+        };
+
+        // NOTE: Articy exports unset external ids as 0x0
+        (!key.is_empty() && key != "0x0").then_some(key)
+    }
+}
+
+/// The visited/finished nodes of an interpreter, keyed in a way that survives re-exports
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SeenContent {
+    pub key: PersistenceKey,
+    pub visited: Vec<String>,
+    pub finished: Vec<String>,
+}
+
+impl File {
+    /// A hash of what the flow is made of (ids, texts, expressions and connections), positions, colors
+    /// and the like don't affect it. It only changes when the export itself does, across runs and platforms.
+    pub fn content_hash(&self) -> u64 {
+        let mut models = self.get_models();
+        models.sort_by_cached_key(|model| model.id().to_inner());
+
+        let mut hasher = ContentHasher::default();
+        for model in models {
+            hasher.write(model.id().as_str());
+            hasher.write(model.type_name());
+            hasher.write(&model.technical_name().unwrap_or_default());
+
+            for field in TextField::ALL {
+                hasher.write(&model.text_field(field).unwrap_or_default());
+            }
+
+            if let Model::Instruction { expression, .. } | Model::Condition { expression, .. } =
+                model
+            {
+                hasher.write(expression);
+            }
+
+            for pins in [model.input_pins(), model.output_pins()] {
+                for pin in pins.map(Vec::as_slice).unwrap_or_default() {
+                    hasher.write_pin(pin);
+                }
+            }
+        }
+
+        hasher.0
+    }
+}
+
+/// FNV-1a, unlike `DefaultHasher` its output is guaranteed to stay the same between Rust versions
+struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        ContentHasher(0xcbf29ce484222325)
+    }
+}
+
+impl ContentHasher {
+    fn write(&mut self, value: &str) {
+        // NOTE: The length goes in first so ("ab", "c") and ("a", "bc") hash differently
+        for byte in (value.len() as u64)
+            .to_le_bytes()
+            .iter()
+            .chain(value.as_bytes())
+        {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    fn write_pin(&mut self, pin: &Pin) {
+        self.write(pin.id.as_str());
+        self.write(&pin.text);
+
+        for connection in &pin.connections {
+            self.write(connection.target.as_str());
+            self.write(connection.target_pin.as_str());
+        }
+    }
+}
+
+/// What to do when a snapshot is restored against an export it wasn't created with
+#[cfg(feature = "interpreter")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContentMismatch {
+    Ignore,
+    /// Restores the snapshot after recording an `EventKind::ContentMismatch`, see `Interpreter::recent_events`
+    #[default]
+    Warn,
+    /// Doesn't restore anything and returns `Error::ContentHashMismatch`
+    Fail,
+}
+
+/// Everything needed to pick a playthrough back up, see `Interpreter::snapshot`
+#[cfg(feature = "interpreter")]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    /// `File::content_hash` of the export the snapshot was created with
+    pub content_hash: u64,
+    pub cursor: Option<Id>,
+    pub variables: Variables,
+    pub seen: SeenContent,
+}
+
+#[cfg(feature = "interpreter")]
+impl Interpreter {
+    pub fn snapshot(&self, key: PersistenceKey) -> Snapshot {
+        Snapshot {
+            content_hash: self.file.content_hash(),
+            cursor: self.cursor.clone(),
+            variables: self.export_variables(None),
+            seen: self.export_seen(key),
+        }
+    }
+
+    /// Restores a snapshot in place of the current playthrough, returns the visited/finished keys that don't exist
+    /// in the loaded file (see `import_seen`). The cursor is only restored if its node still exists.
+    /// NOTE: The state is rebuilt like with `reset`, so functions set on it directly have to be set again.
+    pub fn restore(
+        &mut self,
+        snapshot: &Snapshot,
+        on_mismatch: ContentMismatch,
+    ) -> Result<Vec<String>, Error> {
+        let content_hash = self.file.content_hash();
+
+        if content_hash != snapshot.content_hash {
+            match on_mismatch {
+                ContentMismatch::Ignore => {}
+                ContentMismatch::Warn => self.record_event(EventKind::ContentMismatch {
+                    expected: snapshot.content_hash,
+                    found: content_hash,
+                }),
+                ContentMismatch::Fail => {
+                    return Err(Error::ContentHashMismatch {
+                        expected: snapshot.content_hash,
+                        found: content_hash,
+                    })
+                }
+            }
+        }
+
+        // Variables the snapshot doesn't know of go back to their defaults instead of keeping newer values
+        self.reset();
+        self.visited.clear();
+        self.finished.clear();
+
+        self.import_variables(&snapshot.variables)?;
+        self.cursor = snapshot
+            .cursor
+            .clone()
+            .filter(|cursor| self.file.get_model(cursor).is_some());
+        if let Some(cursor) = self.cursor.clone() {
+            self.enter_local_scope(&cursor);
+        }
+
+        Ok(self.import_seen(&snapshot.seen))
+    }
+
+    /// Exports the visited/finished nodes, nodes without the requested key are stored by their id instead
+    pub fn export_seen(&self, key: PersistenceKey) -> SeenContent {
+        let to_keys = |ids: &[Id]| {
+            ids.iter()
+                .map(|id| {
+                    self.file
+                        .get_model(id)
+                        .and_then(|model| key.of(model))
+                        .unwrap_or_else(|| id.to_inner())
+                })
+                .collect()
+        };
+
+        SeenContent {
+            key,
+            visited: to_keys(&self.visited),
+            finished: to_keys(&self.finished),
+        }
+    }
+
+    /// Remaps the stored keys onto the ids of the currently loaded file and marks them as visited/finished,
+    /// returns the keys that don't exist (anymore) in the file.
+    pub fn import_seen(&mut self, seen: &SeenContent) -> Vec<String> {
+        let ids = self
+            .file
+            .get_models()
+            .into_iter()
+            .filter_map(|model| Some((seen.key.of(model)?, model.id())))
+            .collect::<HashMap<String, Id>>();
+
+        let mut missing = vec![];
+        let mut remap = |keys: &[String], target: &mut Vec<Id>| {
+            for key in keys {
+                let id = ids.get(key).cloned().or_else(|| {
+                    let id = Id(key.as_str().into());
+                    self.file.get_model(&id).map(|_| id)
+                });
+
+                match id {
+                    Some(id) if !target.contains(&id) => target.push(id),
+                    Some(_) => {}
+                    None => missing.push(key.clone()),
+                }
+            }
+        };
+
+        remap(&seen.visited, &mut self.visited);
+        remap(&seen.finished, &mut self.finished);
+
+        missing
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use crate::localization::TextField;
+use crate::types::{File, Id, Model, ModelType, PackageScope, Pin, Type};
+
+impl File {
+    /// Starts a query over the models of the default package, filters are applied lazily once iterated
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            file: self,
+            filters: vec![],
+        }
+    }
+
+    /// Case insensitive search through all text fields (text, menu text, stage directions, display name),
+    /// a model matching in several fields is reported once per field.
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        let query = query.to_lowercase();
+        let mut hits = vec![];
+
+        if query.is_empty() {
+            return hits;
+        }
+
+        for model in &self.get_default_package().models {
+            for field in TextField::ALL {
+                let Some(text) = model.text_field(field) else {
+                    continue;
+                };
+
+                if let Some(start) = find_case_insensitive(&text, &query) {
+                    hits.push(SearchHit {
+                        model,
+                        field,
+                        snippet: snippet(&text, start, query.chars().count()),
+                    });
+                }
+            }
+        }
+
+        hits
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit<'a> {
+    pub model: &'a Model,
+    pub field: TextField,
+    /// The text around the match, shortened with `…` on the cut off sides
+    pub snippet: String,
+}
+
+const SNIPPET_CONTEXT: usize = 30;
+
+/// How many parents are followed up the hierarchy before giving up on a malformed parent chain
+pub(crate) const MAX_HIERARCHY_DEPTH: usize = 64;
+
+/// Byte offset of the first match of the (already lowercased) query
+fn find_case_insensitive(text: &str, query: &str) -> Option<usize> {
+    text.char_indices().map(|(index, _)| index).find(|index| {
+        let mut rest = text[*index..].chars().flat_map(char::to_lowercase);
+        query
+            .chars()
+            .all(|query_char| rest.next() == Some(query_char))
+    })
+}
+
+fn snippet(text: &str, start: usize, length: usize) -> String {
+    let before = text[..start].chars().rev().take(SNIPPET_CONTEXT).count();
+    let chars = text.chars().collect::<Vec<char>>();
+    let start = text[..start].chars().count();
+    let from = start - before;
+    let to = (start + length + SNIPPET_CONTEXT).min(chars.len());
+
+    format!(
+        "{}{}{}",
+        if from > 0 { "…" } else { "" },
+        chars[from..to].iter().collect::<String>(),
+        if to < chars.len() { "…" } else { "" }
+    )
+}
+
+type ModelFilter<'a> = dyn Fn(&Model) -> bool + 'a;
+
+pub struct Query<'a> {
+    file: &'a File,
+    filters: Vec<Box<ModelFilter<'a>>>,
+}
+
+impl<'a> Query<'a> {
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&Model) -> bool + 'a,
+    {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn of_type(self, kind: Type) -> Self {
+        let kind = kind.to_string();
+
+        self.filter(move |model| model.type_name() == kind)
+    }
+
+    pub fn with_speaker(self, speaker_id: &Id) -> Self {
+        let speaker_id = speaker_id.clone();
+
+        self.filter(move |model| {
+            matches!(model, Model::DialogueFragment { speaker, .. } if speaker == &speaker_id)
+        })
+    }
+
+    /// Case sensitive match on the text of the model
+    pub fn text_contains(self, text: &str) -> Self {
+        let text = text.to_owned();
+
+        self.filter(move |model| {
+            model
+                .text()
+                .is_some_and(|model_text| model_text.contains(&text))
+        })
+    }
+
+    /// Only models somewhere below the given Flow/FlowFragment/Dialogue
+    pub fn in_flow(self, flow_id: &Id) -> Self {
+        let file = self.file;
+        let flow_id = flow_id.clone();
+
+        self.filter(move |model| {
+            let mut parent = model.parent();
+
+            // NOTE: Bounded in case of a malformed parent chain
+            for _ in 0..MAX_HIERARCHY_DEPTH {
+                if parent == flow_id {
+                    return true;
+                }
+
+                match file.get_model(&parent) {
+                    Some(model) => parent = model.parent(),
+                    None => return false,
+                }
+            }
+
+            false
+        })
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = &'a Model> {
+        let filters = self.filters;
+
+        self.file
+            .get_default_package()
+            .models
+            .iter()
+            .filter(move |model| filters.iter().all(|filter| filter(model)))
+    }
+
+    pub fn first(self) -> Option<&'a Model> {
+        self.iter().next()
+    }
+
+    pub fn count(self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn collect(self) -> Vec<&'a Model> {
+        self.iter().collect()
+    }
+}
+
+/// A read-only handle to a file that's cheap to clone and can be shared between threads,
+/// e.g for a codex or quest tracker querying while the Interpreter drives the flow.
+/// `FileQuery::new(interpreter.file.clone())` shares the file the Interpreter runs on.
+#[derive(Debug, Clone)]
+pub struct FileQuery {
+    file: Arc<File>,
+}
+
+impl FileQuery {
+    pub fn new(file: Arc<File>) -> Self {
+        FileQuery { file }
+    }
+
+    pub fn get_model(&self, id: &Id) -> Option<&Model> {
+        self.file.get_model(id)
+    }
+
+    pub fn get_model_by_short_id(&self, short_id: u32) -> Option<&Model> {
+        self.file.get_model_by_short_id(short_id)
+    }
+
+    pub fn find_model(&self, id_or_technical_name: &str) -> Option<&Model> {
+        self.file.find_model(id_or_technical_name)
+    }
+
+    pub fn get_models(&self) -> Vec<&Model> {
+        self.file.get_models()
+    }
+
+    pub fn get_models_of_type<K: ModelType>(&self, kind: K) -> Vec<&Model> {
+        self.file.get_models_of_type(kind)
+    }
+
+    pub fn get_pin(&self, id: &Id) -> Option<&Pin> {
+        self.file.get_pin(id)
+    }
+
+    pub fn in_package(&self, name: &str) -> PackageScope<'_> {
+        self.file.in_package(name)
+    }
+
+    pub fn resolve_references<'a>(&'a self, model: &'a Model) -> Vec<(&'a str, &'a Model)> {
+        self.file.resolve_references(model)
+    }
+
+    pub fn query(&self) -> Query<'_> {
+        self.file.query()
+    }
+
+    pub fn search(&self, query: &str) -> Vec<SearchHit<'_>> {
+        self.file.search(query)
+    }
+}
+
+impl From<File> for FileQuery {
+    fn from(file: File) -> Self {
+        FileQuery::new(Arc::new(file))
+    }
+}
@@ -0,0 +1,183 @@
+use crate::flow::pins;
+use crate::types::{File, Id, Model};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DialogueReport {
+    pub dialogue: Id,
+    pub node_count: usize,
+    /// Hubs and DialogueFragments offering more than one way forward
+    pub choice_points: usize,
+    /// The amount of options over all choice points
+    pub choices: usize,
+    /// Amount of nodes on the shortest/longest way from the start of the dialogue to one of its endings
+    pub shortest_path: Option<usize>,
+    pub longest_path: Option<usize>,
+    pub endings: Vec<EndingReport>,
+    /// The walk gave up after its step limit, the paths and endings are only the ones found until then
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndingReport {
+    /// The last node before the dialogue is left
+    pub node: Id,
+    /// The output pin of the Dialogue the flow exits through
+    pub exit_pin: Id,
+    /// Pin conditions and Condition outcomes along the shortest way to this ending,
+    /// Conditions that have to be false are written as `!(expression)`
+    pub conditions: Vec<String>,
+    pub shortest_path: usize,
+}
+
+/// How many nodes `dialogue_report` steps through before giving up, every loop-free path is walked
+/// which grows exponentially with the amount of choices in a row
+pub const MAX_WALK_STEPS: usize = 100_000;
+
+/// Branching metrics of a single Dialogue, paths loop-free and ignoring the state
+pub fn dialogue_report(file: &File, dialogue_id: &Id) -> Option<DialogueReport> {
+    dialogue_report_with_limit(file, dialogue_id, MAX_WALK_STEPS)
+}
+
+/// Same as `dialogue_report`, stepping through at most `max_steps` nodes
+pub fn dialogue_report_with_limit(
+    file: &File,
+    dialogue_id: &Id,
+    max_steps: usize,
+) -> Option<DialogueReport> {
+    let dialogue = file.get_model(dialogue_id)?;
+
+    let mut report = DialogueReport {
+        dialogue: dialogue_id.clone(),
+        node_count: 0,
+        choice_points: 0,
+        choices: 0,
+        shortest_path: None,
+        longest_path: None,
+        endings: vec![],
+        truncated: false,
+    };
+
+    for model in file
+        .get_models()
+        .into_iter()
+        .filter(|model| &model.parent() == dialogue_id)
+    {
+        report.node_count += 1;
+
+        if let Model::Hub { .. } | Model::DialogueFragment { .. } = model {
+            let options = pins(model, "output_pins")
+                .iter()
+                .map(|pin| pin.connections.len())
+                .sum::<usize>();
+
+            if options > 1 {
+                report.choice_points += 1;
+                report.choices += options;
+            }
+        }
+    }
+
+    let mut walk = Walk {
+        file,
+        dialogue_id,
+        path: vec![],
+        conditions: vec![],
+        steps_left: max_steps,
+        report: &mut report,
+    };
+    for pin in pins(dialogue, "input_pins") {
+        for connection in pin.connections {
+            walk.enter(&connection.target, &connection.target_pin);
+        }
+    }
+
+    Some(report)
+}
+
+struct Walk<'a> {
+    file: &'a File,
+    dialogue_id: &'a Id,
+    path: Vec<Id>,
+    conditions: Vec<String>,
+    steps_left: usize,
+    report: &'a mut DialogueReport,
+}
+
+impl Walk<'_> {
+    fn enter(&mut self, id: &Id, pin_id: &Id) {
+        let Some(model) = self.file.get_model(id) else {
+            return;
+        };
+
+        if self.path.contains(id) {
+            return;
+        }
+
+        if self.steps_left == 0 {
+            self.report.truncated = true;
+            return;
+        }
+        self.steps_left -= 1;
+
+        let condition = pins(model, "input_pins")
+            .into_iter()
+            .find(|pin| &pin.id == pin_id && !pin.text.is_empty())
+            .map(|pin| pin.text);
+
+        self.path.push(id.clone());
+        self.conditions.extend(condition.clone());
+
+        let output_pins = pins(model, "output_pins");
+        for (index, pin) in output_pins.iter().enumerate() {
+            let branch = match model {
+                Model::Condition { expression, .. } if index == 0 => Some(expression.clone()),
+                Model::Condition { expression, .. } => Some(format!("!({expression})")),
+                _ => None,
+            };
+            self.conditions.extend(branch.clone());
+
+            for connection in &pin.connections {
+                if &connection.target == self.dialogue_id {
+                    self.reach_ending(id, &connection.target_pin);
+                } else {
+                    self.enter(&connection.target, &connection.target_pin);
+                }
+            }
+
+            if branch.is_some() {
+                self.conditions.pop();
+            }
+        }
+
+        if condition.is_some() {
+            self.conditions.pop();
+        }
+        self.path.pop();
+    }
+
+    fn reach_ending(&mut self, node: &Id, exit_pin: &Id) {
+        let length = self.path.len();
+        let report = &mut *self.report;
+
+        report.shortest_path = Some(report.shortest_path.map_or(length, |path| path.min(length)));
+        report.longest_path = Some(report.longest_path.map_or(length, |path| path.max(length)));
+
+        match report
+            .endings
+            .iter_mut()
+            .find(|ending| &ending.node == node && &ending.exit_pin == exit_pin)
+        {
+            Some(ending) if ending.shortest_path <= length => {}
+            Some(ending) => {
+                ending.conditions = self.conditions.clone();
+                ending.shortest_path = length;
+            }
+            None => report.endings.push(EndingReport {
+                node: node.clone(),
+                exit_pin: exit_pin.clone(),
+                conditions: self.conditions.clone(),
+                shortest_path: length,
+            }),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+
+use crate::types::{File, VariableValue};
+
+/// An identifier that can be used in Condition and Instruction expressions
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ExpressionSymbol {
+    /// As written in an expression, `namespace.variable` for global variables
+    pub name: String,
+    pub kind: SymbolKind,
+    pub description: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum SymbolKind {
+    /// A global variable, `value_type` is one of `Boolean`, `Integer` or `String`
+    Variable {
+        value_type: String,
+        default: VariableValue,
+    },
+    ScriptMethod {
+        parameter_types: Vec<String>,
+        return_type: String,
+    },
+    /// A function the expression evaluator provides, `signature` is meant for display only
+    BuiltIn { signature: String },
+}
+
+/// The functions evalexpr provides with the features this crate enables, as (name, signature, description)
+const BUILT_INS: &[(&str, &str, &str)] = &[
+    (
+        "min",
+        "min(a, b, ...) -> number",
+        "Smallest of the arguments",
+    ),
+    (
+        "max",
+        "max(a, b, ...) -> number",
+        "Largest of the arguments",
+    ),
+    ("floor", "floor(x) -> float", "Rounds down"),
+    (
+        "round",
+        "round(x) -> float",
+        "Rounds to the nearest integer",
+    ),
+    ("ceil", "ceil(x) -> float", "Rounds up"),
+    (
+        "if",
+        "if(condition, then, else) -> value",
+        "`then` if the condition holds, `else` otherwise",
+    ),
+    ("len", "len(value) -> int", "Length of a string or tuple"),
+    (
+        "typeof",
+        "typeof(value) -> string",
+        "Name of the value's type",
+    ),
+    (
+        "str::from",
+        "str::from(value) -> string",
+        "Converts a value to a string",
+    ),
+    (
+        "str::to_lowercase",
+        "str::to_lowercase(s) -> string",
+        "Lowercases a string",
+    ),
+    (
+        "str::to_uppercase",
+        "str::to_uppercase(s) -> string",
+        "Uppercases a string",
+    ),
+    (
+        "str::trim",
+        "str::trim(s) -> string",
+        "Strips surrounding whitespace",
+    ),
+    ("math::sqrt", "math::sqrt(x) -> float", "Square root"),
+    (
+        "math::pow",
+        "math::pow(x, y) -> float",
+        "`x` to the power of `y`",
+    ),
+];
+
+impl File {
+    /// Every identifier an expression can refer to: the global variables with their types and defaults,
+    /// the project's script methods and the built-in functions, in that order.
+    /// Meant for autocompletion and to check scripts before running them.
+    pub fn expression_symbols(&self) -> Vec<ExpressionSymbol> {
+        let variables = self.global_variables.iter().flat_map(|global_variable| {
+            global_variable
+                .variables
+                .iter()
+                .map(|variable| ExpressionSymbol {
+                    name: format!("{}.{}", global_variable.namespace, variable.name),
+                    kind: SymbolKind::Variable {
+                        value_type: value_type(&variable.value).to_owned(),
+                        default: variable.value.clone(),
+                    },
+                    description: variable.description.clone(),
+                })
+        });
+
+        let script_methods = self.script_methods.iter().map(|method| ExpressionSymbol {
+            name: method.name.clone(),
+            kind: SymbolKind::ScriptMethod {
+                parameter_types: method.parameter_types.clone(),
+                return_type: method.return_type.clone(),
+            },
+            description: String::new(),
+        });
+
+        let built_ins = BUILT_INS
+            .iter()
+            .map(|(name, signature, description)| ExpressionSymbol {
+                name: name.to_string(),
+                kind: SymbolKind::BuiltIn {
+                    signature: signature.to_string(),
+                },
+                description: description.to_string(),
+            });
+
+        variables.chain(script_methods).chain(built_ins).collect()
+    }
+}
+
+fn value_type(value: &VariableValue) -> &'static str {
+    match value {
+        VariableValue::Boolean(_) => "Boolean",
+        VariableValue::Integer(_) => "Integer",
+        VariableValue::String(_) => "String",
+        VariableValue::Unknown => "Unknown",
+    }
+}
@@ -0,0 +1,87 @@
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::Id;
+use crate::StateValue;
+
+/// What happened during a playthrough, recorded by the interpreter when `Interpreter::transcript` is set
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Transcript {
+    pub entries: Vec<TranscriptEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TranscriptEntry {
+    pub at: SystemTime,
+    pub kind: TranscriptEntryKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TranscriptEntryKind {
+    /// A DialogueFragment that was reached, texts as shown (translated and overridden)
+    Line {
+        node: Id,
+        speaker: Option<String>,
+        text: String,
+    },
+    Choice {
+        node: Id,
+        label: String,
+    },
+    /// A variable changed by an Instruction, `old` is `None` if it wasn't set before
+    VariableChanged {
+        name: String,
+        old: Option<StateValue>,
+        new: StateValue,
+    },
+}
+
+impl Transcript {
+    pub fn push(&mut self, kind: TranscriptEntryKind) {
+        self.entries.push(TranscriptEntry {
+            at: SystemTime::now(),
+            kind,
+        });
+    }
+
+    /// One entry per line and without timestamps, so transcripts of two playthroughs can be diffed
+    pub fn to_text(&self) -> String {
+        self.render(|kind| match kind {
+            TranscriptEntryKind::Line { speaker, text, .. } => match speaker {
+                Some(speaker) => format!("{speaker}: {text}"),
+                None => text.to_owned(),
+            },
+            TranscriptEntryKind::Choice { label, .. } => format!("> {label}"),
+            TranscriptEntryKind::VariableChanged { name, old, new } => {
+                format!("  {name}: {} -> {new}", display_old(old))
+            }
+        })
+    }
+
+    pub fn to_markdown(&self) -> String {
+        self.render(|kind| match kind {
+            TranscriptEntryKind::Line { speaker, text, .. } => match speaker {
+                Some(speaker) => format!("**{speaker}:** {text}  "),
+                None => format!("{text}  "),
+            },
+            TranscriptEntryKind::Choice { label, .. } => format!("> *{label}*\n"),
+            TranscriptEntryKind::VariableChanged { name, old, new } => {
+                format!("*`{name}`: {} → {new}*  ", display_old(old))
+            }
+        })
+    }
+
+    fn render(&self, line: impl Fn(&TranscriptEntryKind) -> String) -> String {
+        self.entries
+            .iter()
+            .map(|entry| line(&entry.kind) + "\n")
+            .collect()
+    }
+}
+
+fn display_old(old: &Option<StateValue>) -> String {
+    old.as_ref()
+        .map(StateValue::to_string)
+        .unwrap_or_else(|| "unset".to_owned())
+}
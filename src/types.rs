@@ -1,8 +1,14 @@
-#[allow(unreachable_code)]
+use serde::de::DeserializeOwned;
 use serde::de::Error as SerdeError;
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{Map, Value};
-use std::collections::HashMap;
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, OnceLock};
+
+use crate::query::MAX_HIERARCHY_DEPTH;
+use crate::warnings::{self, LoadWarning};
 
 use serde_enum_str::{
     Deserialize_enum_str as DeserializeString, Serialize_enum_str as SerializeString,
@@ -21,6 +27,53 @@ pub enum Error {
     NoOutputConnected,
     FailedToSetState,
     FailedToGetState,
+    FailedToDeserializeModel(Id),
+    FailedToParseStringTable,
+    FailedToReadFile,
+    /// The data isn't JSON or isn't shaped like an Articy export, with serde_json's message
+    FailedToParseFile(String),
+    Expression(ExpressionError),
+    /// None of the input pins of the node let the flow in
+    EntryConditionFailed(Id),
+    /// The node can't be part of a flow, e.g an Entity
+    NotStartable(Id),
+    /// More technical nodes in a row than the interpreter allows, with the chain of nodes that were followed
+    TraversalLoop(Vec<Id>),
+    /// A snapshot was created against a different export, with the content hash it expected and the one it found
+    ContentHashMismatch {
+        expected: u64,
+        found: u64,
+    },
+    /// The node at which the interpreter ran out of its `Budget`
+    BudgetExceeded(Id),
+    /// The flow reached a node the interpreter can't step through, e.g a Comment wired into a dialogue
+    UnsupportedNodeType {
+        id: Id,
+        kind: String,
+    },
+    /// The node was expected to have input or output pins but has none, e.g an Entity as a connection target
+    MissingPins {
+        id: Id,
+    },
+}
+
+/// A condition or instruction that couldn't be evaluated
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExpressionError {
+    /// The node the expression belongs to
+    pub node: Id,
+    pub source: ExpressionSource,
+    pub expression: String,
+    /// The message of the underlying evalexpr error
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionSource {
+    /// The script of one of the node's pins
+    Pin(Id),
+    /// The `expression` of a Condition or Instruction
+    Expression,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -30,19 +83,111 @@ pub struct File {
     pub global_variables: Vec<GlobalVariable>,
     pub object_definitions: Vec<Object>,
     pub packages: Vec<Package>,
+    #[serde(default)]
     pub script_methods: Vec<ScriptMethod>,
     pub hierarchy: Hierarchy,
+
+    #[serde(skip)]
+    pub(crate) custom_models: CustomModels,
+    #[serde(skip)]
+    pub(crate) model_index: OnceLock<HashMap<Id, (usize, usize)>>,
+    #[serde(skip)]
+    pub(crate) load_warnings: Vec<LoadWarning>,
+    #[serde(skip)]
+    pub(crate) short_id_index: OnceLock<HashMap<u32, (usize, usize)>>,
+}
+
+/// `Custom` models that were converted into user types through `File::register_model_type`
+#[derive(Default, Clone)]
+pub(crate) struct CustomModels(pub(crate) HashMap<Id, Arc<dyn Any + Send + Sync>>);
+
+impl std::fmt::Debug for CustomModels {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
 }
 
 impl File {
-    pub fn from_buffer(bytes: &Vec<u8>) -> Self {
-        serde_json::from_value(Value::Object(convert_map_to_snake_case(
-            serde_json::from_slice::<Value>(bytes)
-                .expect("to be able to parse articy data into serde_json Value")
-                .as_object()
-                .expect("the articy data to be an object at the root"),
-        )))
-        .expect("to parse snake cased articy data as a File")
+    /// Panics when the bytes aren't an Articy export, see `try_from_buffer`
+    pub fn from_buffer(bytes: &[u8]) -> Self {
+        File::try_from_buffer(bytes).expect("to be able to parse articy data")
+    }
+
+    pub fn try_from_buffer(bytes: &[u8]) -> Result<Self, Error> {
+        let value = serde_json::from_slice::<Value>(bytes)
+            .map_err(|error| Error::FailedToParseFile(error.to_string()))?;
+
+        File::from_value(value)
+    }
+
+    /// Reads the export at the given path, the raw bytes are dropped as soon as they're parsed
+    /// which keeps the peak memory use of large exports down compared to `from_buffer`.
+    /// NOTE: The export is still copied into owned data, it isn't memory-mapped
+    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self, Error> {
+        let value = {
+            let bytes = std::fs::read(path).map_err(|_| Error::FailedToReadFile)?;
+            serde_json::from_slice::<Value>(&bytes)
+                .map_err(|error| Error::FailedToParseFile(error.to_string()))?
+        };
+
+        File::from_value(value)
+    }
+
+    fn from_value(value: Value) -> Result<Self, Error> {
+        let Value::Object(map) = value else {
+            return Err(Error::FailedToParseFile(
+                "expected an object at the root of the export".to_owned(),
+            ));
+        };
+        let value = crate::compat::normalize(Value::Object(convert_map_to_snake_case(map)));
+
+        let (file, load_warnings) = warnings::collect_during(|| {
+            warnings::warn_about_missing_connections(&value);
+            Id::intern_during(|| serde_json::from_value::<File>(value))
+        });
+
+        let mut file = file.map_err(|error| Error::FailedToParseFile(error.to_string()))?;
+        file.load_warnings = load_warnings;
+
+        Ok(file)
+    }
+
+    /// Deserializes every `Model::Custom` of the given type (e.g "QuestNode") into `T`, returns how many were converted.
+    /// The models can afterwards be retrieved with `get_custom_model` / `get_custom_models`.
+    pub fn register_model_type<T>(&mut self, kind: &str) -> Result<usize, Error>
+    where
+        T: DeserializeOwned + Send + Sync + 'static,
+    {
+        let mut converted = vec![];
+
+        for model in self.get_models_of_type(kind) {
+            if let Model::Custom(_, value) = model {
+                let custom_model: T = serde_json::from_value(value.clone())
+                    .ok()
+                    .ok_or_else(|| Error::FailedToDeserializeModel(model.id()))?;
+
+                converted.push((model.id(), Arc::new(custom_model)));
+            }
+        }
+
+        let count = converted.len();
+        for (id, custom_model) in converted {
+            self.custom_models.0.insert(id, custom_model);
+        }
+
+        Ok(count)
+    }
+
+    pub fn get_custom_model<T: 'static>(&self, id: &Id) -> Option<&T> {
+        self.custom_models.0.get(id)?.downcast_ref::<T>()
+    }
+
+    pub fn get_custom_models<T: 'static>(&self) -> Vec<(&Id, &T)> {
+        self.custom_models
+            .0
+            .iter()
+            .filter_map(|(id, custom_model)| Some((id, custom_model.downcast_ref::<T>()?)))
+            .collect()
     }
 
     pub fn get_default_package(&self) -> &Package {
@@ -53,24 +198,22 @@ impl File {
     }
 
     pub fn get_main_flow(&self) -> Option<&Hierarchy> {
-        self.hierarchy.children.as_ref()?.iter().find(|item| {
-            if let Type::Flow = item.kind {
-                true
-            } else {
-                false
-            }
-        })
+        self.hierarchy
+            .children
+            .as_ref()?
+            .iter()
+            .find(|item| matches!(item.kind, Type::Flow))
     }
 
-    pub fn get_models_of_type(&self, kind: &str) -> Vec<&Model> {
+    /// Takes either the name of the type (`"Dialogue"`) or a `Type` (`Type::Dialogue`)
+    pub fn get_models_of_type<K: ModelType>(&self, kind: K) -> Vec<&Model> {
+        let kind = kind.type_name();
+
         // FIXME: Perhaps iterate ALL of the available packages instead of assuming only one
         self.get_default_package()
             .models
             .iter()
-            .filter(|model| match model {
-                Model::Custom(custom_kind, _) => custom_kind == kind,
-                _ => kind == Into::<&str>::into(*model),
-            })
+            .filter(|model| model.type_name() == kind)
             .collect::<Vec<&Model>>()
     }
 
@@ -82,19 +225,167 @@ impl File {
             .collect::<Vec<&Model>>()
     }
 
-    pub fn get_dialogues_in_flow(&self, flow_id: &Id) -> Vec<&Model> {
-        self.get_default_package()
-            .models
-            .iter()
-            .filter_map(|model| {
-                if let Model::Dialogue { parent, .. } = model {
-                    if parent == flow_id {
-                        return Some(model);
+    /// Looks the model up in the default package first, and then in the other packages in the order they were exported,
+    /// so a model shared between packages always resolves to the same one. See `in_package` to look in a specific package.
+    pub fn get_model(&self, id: &Id) -> Option<&Model> {
+        let index = self.model_index.get_or_init(|| {
+            let mut index = HashMap::new();
+
+            for (package_index, package) in self.packages_by_precedence() {
+                for (model_index, model) in package.models.iter().enumerate() {
+                    index
+                        .entry(model.id())
+                        .or_insert((package_index, model_index));
+                }
+            }
+
+            index
+        });
+
+        // NOTE: The packages are public and can be changed after the index was built, so verify what it points at
+        match index
+            .get(id)
+            .and_then(|(package, model)| self.packages.get(*package)?.models.get(*model))
+        {
+            Some(model) if &model.id() == id => Some(model),
+            _ => self
+                .packages_by_precedence()
+                .find_map(|(_, package)| package.models.iter().find(|model| &model.id() == id)),
+        }
+    }
+
+    /// Looks up an input or output pin of any model, following the same package precedence as `get_model`
+    pub fn get_pin(&self, id: &Id) -> Option<&Pin> {
+        self.packages_by_precedence()
+            .flat_map(|(_, package)| &package.models)
+            .flat_map(|model| {
+                model
+                    .input_pins()
+                    .into_iter()
+                    .chain(model.output_pins())
+                    .flatten()
+            })
+            .find(|pin| &pin.id == id)
+    }
+
+    /// Looks a model up by its short id, following the same package precedence as `get_model`
+    pub fn get_model_by_short_id(&self, short_id: u32) -> Option<&Model> {
+        let index = self.short_id_index.get_or_init(|| {
+            let mut index = HashMap::new();
+
+            for (package_index, package) in self.packages_by_precedence() {
+                for (model_index, model) in package.models.iter().enumerate() {
+                    if let Some(short_id) = model.short_id() {
+                        index
+                            .entry(short_id)
+                            .or_insert((package_index, model_index));
                     }
                 }
+            }
 
-                None
+            index
+        });
+
+        // NOTE: Same as for `get_model`, the index is verified as the packages can change after it was built
+        match index
+            .get(&short_id)
+            .and_then(|(package, model)| self.packages.get(*package)?.models.get(*model))
+        {
+            Some(model) if model.short_id() == Some(short_id) => Some(model),
+            _ => self.packages_by_precedence().find_map(|(_, package)| {
+                package
+                    .models
+                    .iter()
+                    .find(|model| model.short_id() == Some(short_id))
+            }),
+        }
+    }
+
+    fn packages_by_precedence(&self) -> impl Iterator<Item = (usize, &Package)> {
+        let packages = self.packages.iter().enumerate();
+
+        packages
+            .clone()
+            .filter(|(_, package)| package.is_default_package)
+            .chain(packages.filter(|(_, package)| !package.is_default_package))
+    }
+
+    /// Lookups limited to the package with the given name, they find nothing if there's no such package
+    pub fn in_package(&self, name: &str) -> PackageScope<'_> {
+        PackageScope {
+            package: self.packages.iter().find(|package| package.name == name),
+        }
+    }
+
+    /// Looks a model up by its id, or by its technical name when no model has that id
+    pub fn find_model(&self, id_or_technical_name: &str) -> Option<&Model> {
+        self.get_model(&Id(id_or_technical_name.into()))
+            .or_else(|| {
+                self.get_default_package()
+                    .models
+                    .iter()
+                    .find(|model| model.technical_name().as_deref() == Some(id_or_technical_name))
             })
+    }
+
+    /// Resolves the preview image of a model to the exported file of its asset,
+    /// `None` if the model has no preview image or the asset isn't part of the export.
+    pub fn resolve_preview_image(&self, model: &Model) -> Option<ResolvedAsset> {
+        let preview_image = model.preview_image()?;
+
+        if preview_image.asset.is_empty() {
+            return None;
+        }
+
+        match self.get_model(&preview_image.asset.to_id())? {
+            Model::Asset { id, asset_ref, .. } => Some(ResolvedAsset {
+                asset: id.clone(),
+                path: asset_ref.clone(),
+                view_box: preview_image.view_box.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// The models referenced from the template fields of a model (e.g the Entity giving a quest), as (field, model).
+    /// Reference fields are exported as ids, or lists of ids for reference strips; ids of models that aren't
+    /// part of the export are left out.
+    pub fn resolve_references<'a>(&'a self, model: &'a Model) -> Vec<(&'a str, &'a Model)> {
+        let features: Vec<&Value> = match model {
+            Model::Custom(_, value) => value
+                .get("template")
+                .and_then(Value::as_object)
+                .into_iter()
+                .flat_map(|template| template.values())
+                .collect(),
+            _ => model
+                .template()
+                .into_iter()
+                .flat_map(|template| template.values())
+                .collect(),
+        };
+
+        let mut references = vec![];
+        for (field, value) in features.into_iter().filter_map(Value::as_object).flatten() {
+            let ids = match value {
+                Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+
+            for id in ids.into_iter().filter_map(Value::as_str) {
+                if let Some(referenced) = self.get_model(&Id(id.into())) {
+                    references.push((field.as_str(), referenced));
+                }
+            }
+        }
+
+        references
+    }
+
+    pub fn get_dialogues_in_flow(&self, flow_id: &Id) -> Vec<&Model> {
+        self.packages_by_precedence()
+            .flat_map(|(_, package)| &package.models)
+            .filter(|model| matches!(model, Model::Dialogue { parent, .. } if parent == flow_id))
             .collect::<Vec<&Model>>()
     }
 
@@ -119,12 +410,12 @@ impl File {
         let mut cursor = model.parent();
 
         while &cursor != main_flow_id {
-            let model = self
-                .get_default_package()
-                .models
-                .iter()
-                .find(|model| model.id() == cursor);
-            // .ok_or(Error::NoModel)?;
+            // NOTE: Bounded in case of a malformed parent chain
+            if path.len() > MAX_HIERARCHY_DEPTH {
+                return Err(Error::NoHierarchy);
+            }
+
+            let model = self.get_model(&cursor);
 
             if let Some(model) = model {
                 path.push(model.parent());
@@ -139,6 +430,25 @@ impl File {
         Ok(path)
     }
 
+    /// The exported default values of the global variables as (`namespace.variable`, value),
+    /// optionally only the ones of a single namespace.
+    pub fn default_variables(&self, namespace: Option<&str>) -> Vec<(String, &VariableValue)> {
+        self.global_variables
+            .iter()
+            .filter(|global_variable| {
+                namespace.is_none_or(|namespace| global_variable.namespace == namespace)
+            })
+            .flat_map(|global_variable| {
+                global_variable.variables.iter().map(|variable| {
+                    (
+                        format!("{}.{}", global_variable.namespace, variable.name),
+                        &variable.value,
+                    )
+                })
+            })
+            .collect()
+    }
+
     pub fn get_first_dialogue_fragment_of_dialogue(&self, model: &Model) -> Result<Id, Error> {
         let path = self.get_hierarchy_path_from_model(model)?;
 
@@ -149,9 +459,11 @@ impl File {
             .as_ref()
             .ok_or(Error::NoHierarchy)?
             .iter()
-            .find(|node| match node.kind {
-                Type::DialogueFragment | Type::Condition | Type::Hub | Type::FlowFragment => true,
-                _ => false,
+            .find(|node| {
+                matches!(
+                    node.kind,
+                    Type::DialogueFragment | Type::Condition | Type::Hub | Type::FlowFragment
+                )
             })
             .ok_or(Error::NoHierarchy)?
             .id
@@ -163,14 +475,17 @@ impl File {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Settings {
-    #[serde(deserialize_with = "string_to_bool")]
+    #[serde(deserialize_with = "string_to_bool", serialize_with = "bool_to_string")]
     set_localization: bool,
     // set_text_formatter: String?
-    #[serde(deserialize_with = "string_list_to_node_type_vector")]
+    #[serde(
+        deserialize_with = "string_list_to_node_type_vector",
+        serialize_with = "node_type_vector_to_string_list"
+    )]
     set_included_nodes: Vec<NodeType>,
-    #[serde(deserialize_with = "string_to_bool")]
+    #[serde(deserialize_with = "string_to_bool", serialize_with = "bool_to_string")]
     set_use_script_support: bool,
-    export_version: String,
+    pub(crate) export_version: String,
 }
 
 fn string_to_bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
@@ -183,12 +498,32 @@ where
         "False" | "false" => Ok(false),
         // TODO: Implement a proper Result::Err return value, instead of defaulting to false
         _ => {
-            println!("Couldn't deserialize a &str into a bool, defaulting to `false`");
+            warnings::warn(LoadWarning::InvalidBool(string));
             Ok(false)
         }
     }
 }
 
+fn bool_to_string<S>(value: &bool, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(if *value { "True" } else { "False" })
+}
+
+fn node_type_vector_to_string_list<S>(value: &[NodeType], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_str(
+        &value
+            .iter()
+            .map(|node_type| format!("{node_type:?}"))
+            .collect::<Vec<String>>()
+            .join(", "),
+    )
+}
+
 fn string_list_to_node_type_vector<'de, D>(deserializer: D) -> Result<Vec<NodeType>, D::Error>
 where
     D: Deserializer<'de>,
@@ -209,7 +544,10 @@ where
                 "Assets" => NodeType::Assets,
 
                 // TODO: Implement a proper Result::Err return value, instead of defaulting to Unknown
-                _ => NodeType::Unknown,
+                unknown => {
+                    warnings::warn(LoadWarning::UnknownNodeType(unknown.to_owned()));
+                    NodeType::Unknown
+                }
             }
         })
         .collect())
@@ -225,17 +563,17 @@ pub struct Project {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct GlobalVariable {
-    namespace: String,
-    description: String,
-    variables: Vec<Variable>,
+    pub(crate) namespace: String,
+    pub(crate) description: String,
+    pub(crate) variables: Vec<Variable>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(try_from = "Value")]
+#[serde(try_from = "Value", into = "Value")]
 pub struct Variable {
-    name: String,
-    value: VariableValue,
-    description: String,
+    pub(crate) name: String,
+    pub(crate) value: VariableValue,
+    pub(crate) description: String,
 }
 
 #[derive(Debug, Clone)]
@@ -285,14 +623,14 @@ impl TryFrom<Value> for Variable {
                 "Boolean" => match variable_value {
                     "True" | "true" => VariableValue::Boolean(true),
                     "False" | "false" => VariableValue::Boolean(false),
-                    _ => panic!("Invalid value for boolean: \"{variable_value}\""),
+                    _ => return Err(DeserializationError::UnexpectedType),
                 },
                 "Integer" => match variable_value.parse::<i32>() {
                     Ok(integer) => VariableValue::Integer(integer),
-                    Err(_) => panic!("Invalid value for boolean: \"{variable_value}\""),
+                    Err(_) => return Err(DeserializationError::UnexpectedType),
                 },
                 "String" => VariableValue::String(variable_value.to_string()),
-                _type => unimplemented!("Didn't implement type \"{_type}\" for VariableValue"),
+                _ => return Err(DeserializationError::UnexpectedType),
             },
 
             description: value
@@ -305,13 +643,32 @@ impl TryFrom<Value> for Variable {
     }
 }
 
+impl From<Variable> for Value {
+    fn from(variable: Variable) -> Value {
+        let (kind, value) = match variable.value {
+            VariableValue::Boolean(true) => ("Boolean", "True".to_owned()),
+            VariableValue::Boolean(false) => ("Boolean", "False".to_owned()),
+            VariableValue::Integer(integer) => ("Integer", integer.to_string()),
+            VariableValue::String(string) => ("String", string),
+            VariableValue::Unknown => ("Unknown", "".to_owned()),
+        };
+
+        serde_json::json!({
+            "variable": variable.name,
+            "type": kind,
+            "value": value,
+            "description": variable.description,
+        })
+    }
+}
+
 // TODO: Perhaps combine Type + Value together?
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum VariableType {
     Boolean,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum VariableValue {
     // TODO: Remove Unknown and add deserialization error to be exhaustive
     Unknown,
@@ -324,7 +681,7 @@ pub enum VariableValue {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Object {
     pub class: Type,
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type")]
     pub kind: Type,
     pub properties: Option<Vec<ObjectProperty>>,
 }
@@ -332,7 +689,7 @@ pub struct Object {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ObjectProperty {
     property: String,
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type")]
     property_type: Type,
     item_type: Option<Type>,
 }
@@ -392,15 +749,84 @@ pub enum Type {
     Custom(String),
 }
 
+/// Anything naming a type of Model, used to look up models by type
+pub trait ModelType {
+    fn type_name(&self) -> String;
+}
+
+impl ModelType for &str {
+    fn type_name(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ModelType for String {
+    fn type_name(&self) -> String {
+        self.clone()
+    }
+}
+
+impl ModelType for Type {
+    fn type_name(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ModelType for &Type {
+    fn type_name(&self) -> String {
+        self.to_string()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Package {
     pub name: String,
     pub description: String,
     pub is_default_package: bool,
-    #[serde(deserialize_with = "deserialize_model")]
+    #[serde(
+        deserialize_with = "deserialize_model",
+        serialize_with = "serialize_model"
+    )]
     pub models: Vec<Model>,
 }
 
+/// Model lookups within a single package, see `File::in_package`
+#[derive(Debug, Clone, Copy)]
+pub struct PackageScope<'a> {
+    pub package: Option<&'a Package>,
+}
+
+impl<'a> PackageScope<'a> {
+    pub fn get_models(&self) -> Vec<&'a Model> {
+        self.package
+            .map(|package| package.models.iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn get_models_of_type<K: ModelType>(&self, kind: K) -> Vec<&'a Model> {
+        let kind = kind.type_name();
+
+        self.get_models()
+            .into_iter()
+            .filter(|model| model.type_name() == kind)
+            .collect()
+    }
+
+    pub fn get_model(&self, id: &Id) -> Option<&'a Model> {
+        self.package?.models.iter().find(|model| &model.id() == id)
+    }
+
+    /// Looks a model up by its id, or by its technical name when no model has that id
+    pub fn find_model(&self, id_or_technical_name: &str) -> Option<&'a Model> {
+        self.get_model(&Id(id_or_technical_name.into()))
+            .or_else(|| {
+                self.get_models()
+                    .into_iter()
+                    .find(|model| model.technical_name().as_deref() == Some(id_or_technical_name))
+            })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, IntoStaticStr)]
 #[serde(
 //     // // rename_all(deserialize = "PascalCase"),
@@ -425,6 +851,11 @@ pub enum Model {
 
         input_pins: Vec<Pin>,
         output_pins: Vec<Pin>,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
     DialogueFragment {
         id: Id,
@@ -447,6 +878,9 @@ pub enum Model {
         output_pins: Vec<Pin>,
 
         template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Hub {
@@ -465,6 +899,11 @@ pub enum Model {
 
         input_pins: Vec<Pin>,
         output_pins: Vec<Pin>,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     FlowFragment {
@@ -485,6 +924,11 @@ pub enum Model {
 
         input_pins: Vec<Pin>,
         output_pins: Vec<Pin>,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Dialogue {
@@ -504,6 +948,11 @@ pub enum Model {
         short_id: ShortId,
         input_pins: Vec<Pin>,
         output_pins: Vec<Pin>,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Entity {
@@ -521,6 +970,11 @@ pub enum Model {
         size: Size,
         z_index: f32,
         short_id: ShortId,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Comment {
@@ -538,6 +992,11 @@ pub enum Model {
         z_index: f32,
         size: Size,
         short_id: ShortId,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Condition {
@@ -557,6 +1016,30 @@ pub enum Model {
 
         input_pins: Vec<Pin>,
         output_pins: Vec<Pin>,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
+    },
+
+    Asset {
+        id: Id,
+        parent: Id,
+        technical_name: String,
+
+        display_name: String,
+        external_id: Id,
+        short_id: ShortId,
+        /// Path of the asset's file, relative to the export
+        asset_ref: String,
+        #[serde(default)]
+        category: String,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     UserFolder {
@@ -564,6 +1047,11 @@ pub enum Model {
         parent: Id,
         technical_name: String,
         external_id: Id,
+
+        template: Option<HashMap<String, Value>>,
+        #[cfg(feature = "raw-json")]
+        #[serde(skip)]
+        raw: Option<Arc<Value>>,
     },
 
     Custom(String, Value),
@@ -571,92 +1059,138 @@ pub enum Model {
 
 use convert_case::{Case, Casing};
 
-fn convert_map_to_snake_case(map: &Map<String, Value>) -> Map<String, Value> {
-    let mut tmp = Vec::with_capacity(map.len());
-    let mut new_map = Map::new();
-    for (key, val) in map.into_iter() {
-        tmp.push((key.to_case(Case::Snake), val));
-    }
-    for (key, val) in tmp {
-        match val {
-            Value::Object(object) => {
-                new_map.insert(key, Value::Object(convert_map_to_snake_case(object)));
-            }
-            Value::Array(array) => {
-                new_map.insert(
-                    key,
-                    Value::Array(
-                        array
-                            .into_iter()
-                            .map(|value| match value {
-                                Value::Object(object) => {
-                                    Value::Object(convert_map_to_snake_case(object))
-                                }
-                                _ => value.clone(),
-                            })
-                            .collect::<Vec<Value>>(),
-                    ),
-                );
-            }
-            _ => {
-                new_map.insert(key, val.clone());
-            }
-        }
-    }
+/// Converts the keys of the map (and of nested objects) to snake case, values are moved over instead of copied
+/// so the export isn't held in memory twice.
+fn convert_map_to_snake_case(map: Map<String, Value>) -> Map<String, Value> {
+    map.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::Object(object) => Value::Object(convert_map_to_snake_case(object)),
+                Value::Array(array) => Value::Array(
+                    array
+                        .into_iter()
+                        .map(|value| match value {
+                            Value::Object(object) => {
+                                Value::Object(convert_map_to_snake_case(object))
+                            }
+                            value => value,
+                        })
+                        .collect::<Vec<Value>>(),
+                ),
+                value => value,
+            };
 
-    new_map
+            (key.to_case(Case::Snake), value)
+        })
+        .collect()
 }
 
+/// The kinds with a `Model` variant of their own, other kinds are expected to end up as `Model::Custom`
+const TYPED_MODELS: [&str; 10] = [
+    "Instruction",
+    "DialogueFragment",
+    "Hub",
+    "FlowFragment",
+    "Dialogue",
+    "Entity",
+    "Comment",
+    "Condition",
+    "Asset",
+    "UserFolder",
+];
+
 fn deserialize_model<'de, D>(deserializer: D) -> Result<Vec<Model>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    Ok(Value::deserialize(deserializer)?
-        .as_array()
-        .ok_or(DeserializationError::UnexpectedType)
-        .map_err(D::Error::custom)?
-        .iter()
-        .map(|item| {
+    let Value::Array(items) = Value::deserialize(deserializer)? else {
+        return Err(D::Error::custom(DeserializationError::UnexpectedType));
+    };
+
+    items
+        .into_iter()
+        .map(|mut item| {
             // NOTE: This code makes sure that a Model can fallback to a Custom, if you notice certain models going Custom that shouldn't (e.g they're part of the Model enum list), log the `_error` and check the error message.
 
-            let item = if let Some(template) = item.get("template") {
-                let mut item = item.clone();
+            #[cfg(feature = "raw-json")]
+            let raw = Arc::new(item.clone());
+
+            if let Some(template) = item
+                .as_object_mut()
+                .and_then(|item| item.remove("template"))
+            {
+                if let Some(properties) = item.get_mut("properties").and_then(Value::as_object_mut)
+                {
+                    properties.insert("template".to_owned(), template);
+                }
+            }
 
-                item.get_mut("properties")
-                    .unwrap()
-                    .as_object_mut()
-                    .unwrap()
-                    .insert("template".to_owned(), template.clone());
+            let model = match Model::deserialize(&item) {
+                Ok(model) => model,
+                Err(error) => {
+                    let Some(Value::Object(properties)) =
+                        item.get_mut("properties").map(Value::take)
+                    else {
+                        return Err(D::Error::custom("expected the properties of a model"));
+                    };
+                    let properties = convert_map_to_snake_case(properties);
+
+                    let kind = item
+                        .get("type")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| D::Error::custom("expected the type of a model"))?
+                        .to_owned();
+
+                    if TYPED_MODELS.contains(&kind.as_str()) {
+                        warnings::warn(LoadWarning::FellBackToCustom {
+                            id: Id(properties
+                                .get("id")
+                                .and_then(Value::as_str)
+                                .unwrap_or_default()
+                                .into()),
+                            kind: kind.clone(),
+                            reason: error.to_string(),
+                        });
+                    }
 
-                item
-            } else {
-                item.to_owned()
+                    Model::Custom(kind, Value::Object(properties))
+                }
             };
 
-            serde_json::from_value(item.clone()).unwrap_or_else(|_error| {
-                // println!("ERROR: {:?} {error:#?}", item.get("type"));
-                let properties = convert_map_to_snake_case(
-                    item.get("properties")
-                        .expect("properties to be part of a Model Value")
-                        .clone()
-                        .as_object()
-                        .unwrap(),
-                );
-
-                let kind = item
-                    .get("type")
-                    .expect("Type to be part of a Model Value")
-                    .as_str()
-                    .expect("Type to be of type &str")
-                    .to_owned();
-
-                Model::Custom(kind, Value::Object(properties))
-            })
+            #[cfg(feature = "raw-json")]
+            let model = model.with_raw(raw);
+
+            Ok(model)
         })
-        .collect::<Vec<Model>>())
+        .collect::<Result<Vec<Model>, D::Error>>()
+}
+
+fn serialize_model<S>(models: &[Model], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    // NOTE: Custom models are written back in the same shape they were read from, instead of as a `Custom` tuple
+    serializer.collect_seq(models.iter().map(|model| {
+        match model {
+            Model::Custom(kind, properties) => Ok(serde_json::json!({
+                "type": kind,
+                "properties": properties,
+            })),
+            model => serde_json::to_value(model),
+        }
+        .unwrap_or(Value::Null)
+    }))
 }
 
 impl Model {
+    /// The Articy type of the model, e.g "DialogueFragment" or the type of a `Custom` model
+    pub fn type_name(&self) -> &str {
+        match self {
+            Model::Custom(kind, _) => kind,
+            _ => self.into(),
+        }
+    }
+
     pub fn id(&self) -> Id {
         match self {
             Model::FlowFragment { id, .. }
@@ -666,19 +1200,41 @@ impl Model {
             | Model::Comment { id, .. }
             | Model::Condition { id, .. }
             | Model::UserFolder { id, .. }
+            | Model::Asset { id, .. }
             | Model::Entity { id, .. }
             | Model::Instruction { id, .. } => id.clone(),
 
             Model::Custom(_, value) => match value.get("id") {
                 Some(value) => match value.as_str() {
-                    Some(id) => Id(id.to_owned()),
-                    None => Id("Custom Model did not have Id".to_owned()),
+                    Some(id) => Id(id.into()),
+                    None => Id("Custom Model did not have Id".into()),
                 },
-                None => Id("Custom Model did not have Id".to_owned()),
+                None => Id("Custom Model did not have Id".into()),
             },
         }
     }
 
+    /// The short id Articy shows in its UI and printouts, `None` for UserFolders and Custom models without one
+    pub fn short_id(&self) -> Option<u32> {
+        match self {
+            Model::FlowFragment { short_id, .. }
+            | Model::DialogueFragment { short_id, .. }
+            | Model::Hub { short_id, .. }
+            | Model::Dialogue { short_id, .. }
+            | Model::Comment { short_id, .. }
+            | Model::Condition { short_id, .. }
+            | Model::Asset { short_id, .. }
+            | Model::Entity { short_id, .. }
+            | Model::Instruction { short_id, .. } => Some(short_id.value()),
+
+            Model::UserFolder { .. } => None,
+            Model::Custom(_, value) => value
+                .get("short_id")
+                .and_then(Value::as_u64)
+                .and_then(|short_id| u32::try_from(short_id).ok()),
+        }
+    }
+
     pub fn external_id(&self) -> Id {
         match self {
             Model::FlowFragment { external_id, .. }
@@ -688,15 +1244,16 @@ impl Model {
             | Model::Comment { external_id, .. }
             | Model::Condition { external_id, .. }
             | Model::UserFolder { external_id, .. }
+            | Model::Asset { external_id, .. }
             | Model::Entity { external_id, .. }
             | Model::Instruction { external_id, .. } => external_id.clone(),
 
             Model::Custom(_, value) => match value.get("external_id") {
                 Some(value) => match value.as_str() {
-                    Some(external_id) => Id(external_id.to_owned()),
-                    None => Id("Custom Model did not have external_id".to_owned()),
+                    Some(external_id) => Id(external_id.into()),
+                    None => Id("Custom Model did not have external_id".into()),
                 },
-                None => Id("Custom Model did not have external_id".to_owned()),
+                None => Id("Custom Model did not have external_id".into()),
             },
         }
     }
@@ -711,14 +1268,15 @@ impl Model {
             | Model::Condition { parent, .. }
             | Model::Entity { parent, .. }
             | Model::UserFolder { parent, .. }
+            | Model::Asset { parent, .. }
             | Model::Instruction { parent, .. } => parent.clone(),
 
             Model::Custom(_, value) => match value.get("parent") {
                 Some(value) => match value.as_str() {
-                    Some(id) => Id(id.to_owned()),
-                    None => Id("Custom Model did not have Parent Id".to_owned()),
+                    Some(id) => Id(id.into()),
+                    None => Id("Custom Model did not have Parent Id".into()),
                 },
-                None => Id("Custom Model did not have Parent Id".to_owned()),
+                None => Id("Custom Model did not have Parent Id".into()),
             },
         }
     }
@@ -733,7 +1291,24 @@ impl Model {
             | Model::Entity { text, .. }
             | Model::Condition { text, .. }
             | Model::Instruction { text, .. } => Some(text.to_string()),
-            Model::UserFolder { .. } | Model::Custom(..) => None,
+            Model::UserFolder { .. } | Model::Asset { .. } | Model::Custom(..) => None,
+        }
+    }
+
+    pub fn technical_name(&self) -> Option<String> {
+        match self {
+            Model::FlowFragment { technical_name, .. }
+            | Model::DialogueFragment { technical_name, .. }
+            | Model::Hub { technical_name, .. }
+            | Model::Dialogue { technical_name, .. }
+            | Model::Comment { technical_name, .. }
+            | Model::Condition { technical_name, .. }
+            | Model::Entity { technical_name, .. }
+            | Model::UserFolder { technical_name, .. }
+            | Model::Asset { technical_name, .. }
+            | Model::Instruction { technical_name, .. } => Some(technical_name.to_string()),
+
+            Model::Custom(_, value) => Some(value.get("technical_name")?.as_str()?.to_owned()),
         }
     }
 
@@ -743,6 +1318,7 @@ impl Model {
             | Model::Hub { display_name, .. }
             | Model::Dialogue { display_name, .. }
             | Model::Entity { display_name, .. }
+            | Model::Asset { display_name, .. }
             | Model::Condition { display_name, .. }
             | Model::Instruction { display_name, .. } => Some(display_name.to_string()),
 
@@ -753,6 +1329,16 @@ impl Model {
         }
     }
 
+    pub fn preview_image(&self) -> Option<&PreviewImage> {
+        match self {
+            Model::FlowFragment { preview_image, .. }
+            | Model::Dialogue { preview_image, .. }
+            | Model::Entity { preview_image, .. } => Some(preview_image),
+
+            _ => None,
+        }
+    }
+
     pub fn input_pins(&self) -> Option<&Vec<Pin>> {
         match self {
             Model::FlowFragment { input_pins, .. }
@@ -765,6 +1351,7 @@ impl Model {
             Model::UserFolder { .. }
             | Model::Comment { .. }
             | Model::Entity { .. }
+            | Model::Asset { .. }
             | Model::Custom(..) => None,
         }
     }
@@ -781,17 +1368,305 @@ impl Model {
             Model::UserFolder { .. }
             | Model::Entity { .. }
             | Model::Comment { .. }
+            | Model::Asset { .. }
             | Model::Custom(..) => None,
         }
     }
+
+    pub fn position(&self) -> Option<Point> {
+        match self {
+            Model::FlowFragment { position, .. }
+            | Model::DialogueFragment { position, .. }
+            | Model::Hub { position, .. }
+            | Model::Dialogue { position, .. }
+            | Model::Comment { position, .. }
+            | Model::Condition { position, .. }
+            | Model::Entity { position, .. }
+            | Model::Instruction { position, .. } => Some(position.clone()),
+
+            Model::UserFolder { .. } | Model::Asset { .. } => None,
+            Model::Custom(_, value) => serde_json::from_value(value.get("position")?.clone()).ok(),
+        }
+    }
+
+    pub fn size(&self) -> Option<Size> {
+        match self {
+            Model::FlowFragment { size, .. }
+            | Model::DialogueFragment { size, .. }
+            | Model::Hub { size, .. }
+            | Model::Dialogue { size, .. }
+            | Model::Comment { size, .. }
+            | Model::Condition { size, .. }
+            | Model::Entity { size, .. }
+            | Model::Instruction { size, .. } => Some(size.clone()),
+
+            Model::UserFolder { .. } | Model::Asset { .. } => None,
+            Model::Custom(_, value) => serde_json::from_value(value.get("size")?.clone()).ok(),
+        }
+    }
+
+    /// Where the node sits in the flow editor, for every model that is drawn there
+    pub fn layout(&self) -> Option<Layout> {
+        match self {
+            Model::DialogueFragment {
+                position,
+                size,
+                z_index,
+                split_height,
+                ..
+            } => Some(Layout {
+                position: position.clone(),
+                size: size.clone(),
+                z_index: *z_index,
+                split_height: Some(*split_height),
+            }),
+            Model::FlowFragment {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Hub {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Dialogue {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Comment {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Condition {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Entity {
+                position,
+                size,
+                z_index,
+                ..
+            }
+            | Model::Instruction {
+                position,
+                size,
+                z_index,
+                ..
+            } => Some(Layout {
+                position: position.clone(),
+                size: size.clone(),
+                z_index: *z_index,
+                split_height: None,
+            }),
+
+            Model::UserFolder { .. } | Model::Asset { .. } => None,
+            Model::Custom(_, value) => Some(Layout {
+                position: serde_json::from_value(value.get("position")?.clone()).ok()?,
+                size: serde_json::from_value(value.get("size")?.clone()).ok()?,
+                z_index: value.get("z_index").and_then(Value::as_f64).unwrap_or(0.0) as f32,
+                split_height: value
+                    .get("split_height")
+                    .and_then(Value::as_f64)
+                    .map(|split_height| split_height as f32),
+            }),
+        }
+    }
+
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Model::FlowFragment { color, .. }
+            | Model::DialogueFragment { color, .. }
+            | Model::Hub { color, .. }
+            | Model::Dialogue { color, .. }
+            | Model::Comment { color, .. }
+            | Model::Condition { color, .. }
+            | Model::Entity { color, .. }
+            | Model::Instruction { color, .. } => Some(color.clone()),
+
+            Model::UserFolder { .. } | Model::Asset { .. } => None,
+            Model::Custom(_, value) => serde_json::from_value(value.get("color")?.clone()).ok(),
+        }
+    }
+
+    /// Reads any property of the model by name (e.g `split_height` or `SplitHeight`) without matching on the variant,
+    /// template fields can be read as `"Feature.Field"`.
+    pub fn get_property(&self, name: &str) -> Option<Value> {
+        let properties = match self {
+            Model::Custom(_, value) => value.clone(),
+            _ => serde_json::to_value(self).ok()?.get("properties")?.clone(),
+        };
+
+        properties
+            .get(name)
+            .or_else(|| properties.get(name.to_case(Case::Snake)))
+            .cloned()
+            .or_else(|| {
+                let (feature, field) = name.split_once('.')?;
+                self.template_value(feature, field).cloned()
+            })
+    }
+
+    /// The template data of the model, `Custom` models keep theirs inside of the raw Value
+    /// so use `template_value` to read those.
+    pub fn template(&self) -> Option<&HashMap<String, Value>> {
+        match self {
+            Model::FlowFragment { template, .. }
+            | Model::DialogueFragment { template, .. }
+            | Model::Hub { template, .. }
+            | Model::Dialogue { template, .. }
+            | Model::Comment { template, .. }
+            | Model::Condition { template, .. }
+            | Model::UserFolder { template, .. }
+            | Model::Asset { template, .. }
+            | Model::Entity { template, .. }
+            | Model::Instruction { template, .. } => template.as_ref(),
+
+            Model::Custom(..) => None,
+        }
+    }
+
+    /// The model as it was in the export (keys snake cased by `File::from_buffer`), for reading fields the
+    /// typed variants don't cover yet. Only kept with the `raw-json` feature, `Custom` models return their properties.
+    pub fn raw(&self) -> Option<&Value> {
+        #[cfg(feature = "raw-json")]
+        match self {
+            Model::FlowFragment { raw, .. }
+            | Model::DialogueFragment { raw, .. }
+            | Model::Hub { raw, .. }
+            | Model::Dialogue { raw, .. }
+            | Model::Comment { raw, .. }
+            | Model::Condition { raw, .. }
+            | Model::UserFolder { raw, .. }
+            | Model::Asset { raw, .. }
+            | Model::Entity { raw, .. }
+            | Model::Instruction { raw, .. } => raw.as_deref(),
+
+            Model::Custom(_, value) => Some(value),
+        }
+
+        #[cfg(not(feature = "raw-json"))]
+        match self {
+            Model::Custom(_, value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "raw-json")]
+    fn with_raw(mut self, value: Arc<Value>) -> Self {
+        match &mut self {
+            Model::FlowFragment { raw, .. }
+            | Model::DialogueFragment { raw, .. }
+            | Model::Hub { raw, .. }
+            | Model::Dialogue { raw, .. }
+            | Model::Comment { raw, .. }
+            | Model::Condition { raw, .. }
+            | Model::UserFolder { raw, .. }
+            | Model::Asset { raw, .. }
+            | Model::Entity { raw, .. }
+            | Model::Instruction { raw, .. } => *raw = Some(value),
+
+            Model::Custom(..) => {}
+        }
+
+        self
+    }
+
+    /// Looks up a single template field, e.g `template_value("Quest", "QuestName")`.
+    /// Since `File::from_buffer` snake cases all keys, the snake cased names are tried as well.
+    pub fn template_value(&self, feature: &str, field: &str) -> Option<&Value> {
+        let feature = self.template_feature(feature)?;
+
+        feature
+            .get(field)
+            .or_else(|| feature.get(field.to_case(Case::Snake)))
+    }
+
+    /// Deserializes a whole template feature into `T`, e.g `template_as::<SpawnArea>("SpawnArea")`.
+    /// The field names are snake cased, same as for `template_value`.
+    pub fn template_as<T: DeserializeOwned>(&self, feature: &str) -> Option<T> {
+        serde_json::from_value(self.template_feature(feature)?.clone()).ok()
+    }
+
+    fn template_feature(&self, feature: &str) -> Option<&Value> {
+        match self {
+            Model::Custom(_, value) => {
+                let template = value.get("template")?;
+
+                template
+                    .get(feature)
+                    .or_else(|| template.get(feature.to_case(Case::Snake)))
+            }
+            _ => {
+                let template = self.template()?;
+
+                template
+                    .get(feature)
+                    .or_else(|| template.get(&feature.to_case(Case::Snake)))
+            }
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub struct Id(pub String);
+/// NOTE: Ids are repeated all over an export (parents, speakers, pin owners, connection targets),
+/// so while `File::from_buffer` runs every occurrence of an id shares a single allocation.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Id(pub Arc<str>);
+
+thread_local! {
+    static INTERNED_IDS: RefCell<Option<HashSet<Arc<str>>>> = const { RefCell::new(None) };
+}
 
 impl Id {
     pub fn to_inner(&self) -> String {
-        self.0.to_owned()
+        self.0.to_string()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn interned(id: String) -> Self {
+        INTERNED_IDS.with_borrow_mut(|interned| match interned {
+            Some(interned) => match interned.get(id.as_str()) {
+                Some(id) => Id(id.clone()),
+                None => {
+                    let id: Arc<str> = id.into();
+                    interned.insert(id.clone());
+                    Id(id)
+                }
+            },
+            None => Id(id.into()),
+        })
+    }
+
+    /// Runs `load` with ids being interned, the table is dropped afterwards so it doesn't outlive the loaded file
+    fn intern_during<T>(load: impl FnOnce() -> T) -> T {
+        INTERNED_IDS.set(Some(HashSet::new()));
+        let loaded = load();
+        INTERNED_IDS.set(None);
+
+        loaded
+    }
+}
+
+impl Serialize for Id {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Id {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Id::interned(String::deserialize(deserializer)?))
     }
 }
 
@@ -803,17 +1678,17 @@ pub struct Attachment;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PreviewImage {
-    view_box: Rectangle,
-    mode: PreviewImageMode,
-    asset: AssetId,
+    pub view_box: Rectangle,
+    pub mode: PreviewImageMode,
+    pub asset: AssetId,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Rectangle {
-    x: f32,
-    y: f32,
-    w: f32,
-    h: f32,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -824,31 +1699,111 @@ pub enum PreviewImageMode {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AssetId(String);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl AssetId {
+    /// Articy exports `0x0000000000000000` for nodes without a preview image
+    pub fn is_empty(&self) -> bool {
+        self.0
+            .trim_start_matches("0x")
+            .trim_start_matches('0')
+            .is_empty()
+    }
+
+    pub fn to_id(&self) -> Id {
+        Id(self.0.as_str().into())
+    }
+}
+
+/// A preview image resolved to the asset it points at, see `File::resolve_preview_image`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAsset {
+    pub asset: Id,
+    /// The exported file path, relative to the export
+    pub path: String,
+    /// The part of the image shown as preview
+    pub view_box: Rectangle,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Color {
-    r: f32,
-    g: f32,
-    b: f32,
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    // NOTE: Articy doesn't export alpha for most colors, so those are fully opaque
+    #[serde(default = "default_alpha")]
+    pub a: f32,
+}
+
+fn default_alpha() -> f32 {
+    1.0
+}
+
+impl Color {
+    /// Formats the color as `#RRGGBB`, or `#RRGGBBAA` when it isn't fully opaque.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b, a] = self
+            .to_srgb()
+            .map(|channel| (channel * 255.0).round() as u8);
+
+        if a == u8::MAX {
+            format!("#{r:02X}{g:02X}{b:02X}")
+        } else {
+            format!("#{r:02X}{g:02X}{b:02X}{a:02X}")
+        }
+    }
+
+    /// The color as exported by Articy, sRGB channels clamped to `0.0..=1.0`.
+    pub fn to_srgb(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|channel| channel.clamp(0.0, 1.0))
+    }
+
+    /// The color converted to linear space, alpha is left untouched.
+    pub fn to_linear(&self) -> [f32; 4] {
+        let [r, g, b, a] = self.to_srgb();
+        let to_linear = |channel: f32| {
+            if channel <= 0.04045 {
+                channel / 12.92
+            } else {
+                ((channel + 0.055) / 1.055).powf(2.4)
+            }
+        };
+
+        [to_linear(r), to_linear(g), to_linear(b), a]
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ExternalId(String);
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Point {
-    x: f32,
-    y: f32,
+    pub x: f32,
+    pub y: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Size {
-    w: f32,
-    h: f32,
+    pub w: f32,
+    pub h: f32,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Layout {
+    pub position: Point,
+    pub size: Size,
+    pub z_index: f32,
+    /// Only DialogueFragments are split, into their menu text and text
+    pub split_height: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ShortId(u32);
 
+impl ShortId {
+    pub fn value(&self) -> u32 {
+        self.0
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pin {
     pub text: String,
@@ -864,10 +1819,22 @@ pub struct Connection {
     pub label: String,
     pub target_pin: Id,
     pub target: Id,
+    /// Only exported for connections that were given a color
+    #[serde(default)]
+    pub color: Option<Color>,
+    /// Any other fields of the connection, keys snake cased
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct ScriptMethod;
+/// A method registered in the project settings to be callable from expressions
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ScriptMethod {
+    pub name: String,
+    pub return_type: String,
+    pub parameter_types: Vec<String>,
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum NodeType {
@@ -887,7 +1854,21 @@ pub enum NodeType {
 pub struct Hierarchy {
     pub id: Id,
     pub technical_name: String,
-    #[serde(rename(deserialize = "type"))]
+    #[serde(rename = "type")]
     pub kind: Type,
     pub children: Option<Vec<Hierarchy>>,
 }
+
+impl Hierarchy {
+    /// Finds the node with the given id in this node or any of its descendants
+    pub fn find(&self, id: &Id) -> Option<&Hierarchy> {
+        if &self.id == id {
+            return Some(self);
+        }
+
+        self.children
+            .as_ref()?
+            .iter()
+            .find_map(|child| child.find(id))
+    }
+}
@@ -0,0 +1,173 @@
+use crate::flow::next_connections;
+use crate::types::{File, Id, Model};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub id: Id,
+    /// To find the node in Articy or on printouts
+    pub short_id: Option<u32>,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    EmptyText,
+    PlaceholderText,
+    MenuTextTooLong,
+    DuplicateConsecutiveLine,
+    /// An Instruction fanning out to several nodes, only the first open one is followed
+    BranchingInstruction,
+    /// An Instruction without an outgoing connection, the flow dead-ends after it
+    UnconnectedInstruction,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationConfig {
+    /// Markers that shouldn't end up in shipped text, matched case-insensitively
+    pub placeholder_patterns: Vec<String>,
+    /// Maximum amount of characters in a menu text
+    pub max_menu_text_length: usize,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        ValidationConfig {
+            placeholder_patterns: vec!["TODO".into(), "XXX".into(), "PLACEHOLDER".into()],
+            max_menu_text_length: 60,
+        }
+    }
+}
+
+/// Runs all checks over the default package
+pub fn validate(file: &File, config: &ValidationConfig) -> Vec<Issue> {
+    let mut issues = lint_text(file, config);
+    issues.extend(lint_flow(file));
+
+    issues
+}
+
+/// Flow checks: Instructions that branch or dead-end, which the interpreter can't follow the way they're drawn
+pub fn lint_flow(file: &File) -> Vec<Issue> {
+    let mut issues = vec![];
+
+    for model in file.get_models() {
+        let Model::Instruction {
+            id, output_pins, ..
+        } = model
+        else {
+            continue;
+        };
+
+        let targets = output_pins
+            .iter()
+            .flat_map(|pin| &pin.connections)
+            .map(|connection| connection.target.as_str())
+            .collect::<Vec<&str>>();
+
+        match targets.len() {
+            0 => issues.push(Issue {
+                id: id.clone(),
+                short_id: model.short_id(),
+                kind: IssueKind::UnconnectedInstruction,
+                message: "Instruction has no outgoing connection".into(),
+            }),
+            1 => {}
+            _ => issues.push(Issue {
+                id: id.clone(),
+                short_id: model.short_id(),
+                kind: IssueKind::BranchingInstruction,
+                message: format!(
+                    "Instruction connects to {} nodes, only the first open one is followed: {}",
+                    targets.len(),
+                    targets.join(", ")
+                ),
+            }),
+        }
+    }
+
+    issues
+}
+
+/// Text focused checks for writers: empty lines, placeholder markers, overly long menu texts and repeated lines
+pub fn lint_text(file: &File, config: &ValidationConfig) -> Vec<Issue> {
+    let mut issues = vec![];
+    let patterns = config
+        .placeholder_patterns
+        .iter()
+        .map(|pattern| pattern.to_lowercase())
+        .collect::<Vec<String>>();
+
+    for model in file.get_models() {
+        let Model::DialogueFragment {
+            id,
+            text,
+            menu_text,
+            stage_directions,
+            ..
+        } = model
+        else {
+            continue;
+        };
+
+        if text.trim().is_empty() {
+            issues.push(Issue {
+                id: id.clone(),
+                short_id: model.short_id(),
+                kind: IssueKind::EmptyText,
+                message: "DialogueFragment has no text".into(),
+            });
+        }
+
+        for (field, value) in [
+            ("text", text),
+            ("menu text", menu_text),
+            ("stage directions", stage_directions),
+        ] {
+            let lowercase = value.to_lowercase();
+
+            if let Some(pattern) = patterns
+                .iter()
+                .find(|pattern| lowercase.contains(pattern.as_str()))
+            {
+                issues.push(Issue {
+                    id: id.clone(),
+                    short_id: model.short_id(),
+                    kind: IssueKind::PlaceholderText,
+                    message: format!("The {field} contains \"{pattern}\": {value}"),
+                });
+            }
+        }
+
+        let menu_text_length = menu_text.chars().count();
+        if menu_text_length > config.max_menu_text_length {
+            issues.push(Issue {
+                id: id.clone(),
+                short_id: model.short_id(),
+                kind: IssueKind::MenuTextTooLong,
+                message: format!(
+                    "The menu text is {menu_text_length} characters long, the budget is {}",
+                    config.max_menu_text_length
+                ),
+            });
+        }
+
+        for (target, _) in next_connections(model, None) {
+            if let Some(Model::DialogueFragment {
+                text: next_text, ..
+            }) = file.get_model(&target)
+            {
+                if !text.trim().is_empty() && next_text.trim() == text.trim() {
+                    issues.push(Issue {
+                        id: id.clone(),
+                        short_id: model.short_id(),
+                        kind: IssueKind::DuplicateConsecutiveLine,
+                        message: format!("The next line {} repeats the text: {text}", target.0),
+                    });
+                }
+            }
+        }
+    }
+
+    issues
+}
@@ -0,0 +1,163 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::query::MAX_HIERARCHY_DEPTH;
+use crate::types::{File, Id, Model, Pin, Type};
+
+/// Borrowed fields of a `Model::Dialogue`
+#[derive(Debug, Clone, Copy)]
+pub struct DialogueView<'a> {
+    pub model: &'a Model,
+    pub id: &'a Id,
+    pub parent: &'a Id,
+    pub technical_name: &'a str,
+    pub display_name: &'a str,
+    pub text: &'a str,
+    pub input_pins: &'a [Pin],
+    pub output_pins: &'a [Pin],
+    pub template: Option<&'a HashMap<String, Value>>,
+}
+
+/// Borrowed fields of a `Model::DialogueFragment`
+#[derive(Debug, Clone, Copy)]
+pub struct DialogueFragmentView<'a> {
+    pub model: &'a Model,
+    pub id: &'a Id,
+    pub parent: &'a Id,
+    pub technical_name: &'a str,
+    pub speaker: &'a Id,
+    pub text: &'a str,
+    pub menu_text: &'a str,
+    pub stage_directions: &'a str,
+    pub input_pins: &'a [Pin],
+    pub output_pins: &'a [Pin],
+    pub template: Option<&'a HashMap<String, Value>>,
+}
+
+/// A node the flow can be started from, see `File::list_startable_nodes`
+#[derive(Debug, Clone)]
+pub struct StartableNode<'a> {
+    pub model: &'a Model,
+    pub id: Id,
+    pub technical_name: String,
+    pub display_name: String,
+    /// Display names (technical names when empty) of the parents, outermost first
+    pub path: Vec<String>,
+}
+
+impl Model {
+    pub fn as_dialogue(&self) -> Option<DialogueView<'_>> {
+        match self {
+            Model::Dialogue {
+                id,
+                parent,
+                technical_name,
+                display_name,
+                text,
+                input_pins,
+                output_pins,
+                template,
+                ..
+            } => Some(DialogueView {
+                model: self,
+                id,
+                parent,
+                technical_name,
+                display_name,
+                text,
+                input_pins,
+                output_pins,
+                template: template.as_ref(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn as_dialogue_fragment(&self) -> Option<DialogueFragmentView<'_>> {
+        match self {
+            Model::DialogueFragment {
+                id,
+                parent,
+                technical_name,
+                speaker,
+                text,
+                menu_text,
+                stage_directions,
+                input_pins,
+                output_pins,
+                template,
+                ..
+            } => Some(DialogueFragmentView {
+                model: self,
+                id,
+                parent,
+                technical_name,
+                speaker,
+                text,
+                menu_text,
+                stage_directions,
+                input_pins,
+                output_pins,
+                template: template.as_ref(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl File {
+    pub fn dialogues(&self) -> impl Iterator<Item = DialogueView<'_>> {
+        self.get_models_of_type(Type::Dialogue)
+            .into_iter()
+            .filter_map(Model::as_dialogue)
+    }
+
+    pub fn dialogue_fragments(&self) -> impl Iterator<Item = DialogueFragmentView<'_>> {
+        self.get_models_of_type(Type::DialogueFragment)
+            .into_iter()
+            .filter_map(Model::as_dialogue_fragment)
+    }
+
+    /// Every Dialogue, FlowFragment and Hub in export order, e.g to offer a "jump to conversation" menu
+    pub fn list_startable_nodes(&self) -> Vec<StartableNode<'_>> {
+        self.get_models()
+            .into_iter()
+            .filter(|model| {
+                matches!(
+                    model,
+                    Model::Dialogue { .. } | Model::FlowFragment { .. } | Model::Hub { .. }
+                )
+            })
+            .map(|model| StartableNode {
+                model,
+                id: model.id(),
+                technical_name: model.technical_name().unwrap_or_default(),
+                display_name: model.display_name().unwrap_or_default(),
+                path: self.parent_names(model),
+            })
+            .collect()
+    }
+
+    fn parent_names(&self, model: &Model) -> Vec<String> {
+        let mut names = vec![];
+        let mut cursor = self.get_model(&model.parent());
+
+        while let Some(parent) = cursor {
+            if names.len() == MAX_HIERARCHY_DEPTH {
+                break;
+            }
+
+            let name = parent
+                .display_name()
+                .filter(|name| !name.is_empty())
+                .or_else(|| parent.technical_name())
+                .unwrap_or_default();
+            names.push(name);
+            cursor = self.get_model(&parent.parent());
+        }
+
+        names.reverse();
+        names
+    }
+}
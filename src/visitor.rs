@@ -0,0 +1,32 @@
+use crate::types::{File, Hierarchy, Model};
+
+/// Callbacks for `File::accept`, every method has an empty default so only the needed ones have to be implemented
+pub trait Visitor<'a> {
+    fn enter_node(&mut self, _node: &'a Hierarchy, _depth: usize) {}
+
+    fn leave_node(&mut self, _node: &'a Hierarchy, _depth: usize) {}
+
+    /// Called right after `enter_node` for hierarchy nodes that have a model
+    fn visit_model(&mut self, _model: &'a Model, _node: &'a Hierarchy) {}
+}
+
+impl File {
+    /// Walks the hierarchy depth-first, visiting the model of every node along the way
+    pub fn accept<'a, V: Visitor<'a>>(&'a self, visitor: &mut V) {
+        self.walk_node(&self.hierarchy, 0, visitor);
+    }
+
+    fn walk_node<'a, V: Visitor<'a>>(&'a self, node: &'a Hierarchy, depth: usize, visitor: &mut V) {
+        visitor.enter_node(node, depth);
+
+        if let Some(model) = self.get_model(&node.id) {
+            visitor.visit_model(model, node);
+        }
+
+        for child in node.children.iter().flatten() {
+            self.walk_node(child, depth + 1, visitor);
+        }
+
+        visitor.leave_node(node, depth);
+    }
+}
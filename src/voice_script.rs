@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use crate::localization::escape_csv;
+use crate::query::MAX_HIERARCHY_DEPTH;
+use crate::types::{File, Id, Model};
+
+/// All lines of one speaking Entity, grouped per Dialogue
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceScript {
+    pub speaker: Id,
+    pub speaker_name: String,
+    pub scenes: Vec<Scene>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scene {
+    pub dialogue: Id,
+    /// Display names of the enclosing FlowFragments and the Dialogue, e.g `Chapter One / Intro`
+    pub title: String,
+    pub lines: Vec<VoiceLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoiceLine {
+    pub id: Id,
+    pub text: String,
+    pub stage_directions: String,
+}
+
+/// Builds a voice script for every Entity speaking at least one line, lines follow the flow of their Dialogue
+/// with lines that can't be reached from the start of the Dialogue appended at the end.
+pub fn voice_scripts(file: &File) -> Vec<VoiceScript> {
+    let mut scripts: Vec<VoiceScript> = vec![];
+
+    for dialogue in file.get_models_of_type("Dialogue") {
+        let dialogue_id = dialogue.id();
+        let mut ordered = file
+            .iter_flow_from(&dialogue_id)
+            .filter(|model| model.parent() == dialogue_id)
+            .collect::<Vec<&Model>>();
+        let reached = ordered
+            .iter()
+            .map(|model| model.id())
+            .collect::<HashSet<Id>>();
+        ordered.extend(
+            file.get_models()
+                .into_iter()
+                .filter(|model| model.parent() == dialogue_id && !reached.contains(&model.id())),
+        );
+
+        for model in ordered {
+            let Model::DialogueFragment {
+                id,
+                speaker,
+                text,
+                stage_directions,
+                ..
+            } = model
+            else {
+                continue;
+            };
+
+            let script = match scripts.iter().position(|script| &script.speaker == speaker) {
+                Some(index) => &mut scripts[index],
+                None => {
+                    scripts.push(VoiceScript {
+                        speaker: speaker.clone(),
+                        speaker_name: file
+                            .get_model(speaker)
+                            .and_then(|speaker| speaker.display_name())
+                            .unwrap_or_else(|| speaker.to_inner()),
+                        scenes: vec![],
+                    });
+                    scripts.last_mut().expect("script to just have been added")
+                }
+            };
+
+            if script
+                .scenes
+                .last()
+                .is_none_or(|scene| scene.dialogue != dialogue_id)
+            {
+                script.scenes.push(Scene {
+                    dialogue: dialogue_id.clone(),
+                    title: scene_title(file, dialogue),
+                    lines: vec![],
+                });
+            }
+
+            script
+                .scenes
+                .last_mut()
+                .expect("scene to just have been added")
+                .lines
+                .push(VoiceLine {
+                    id: id.clone(),
+                    text: text.clone(),
+                    stage_directions: stage_directions.clone(),
+                });
+        }
+    }
+
+    scripts
+}
+
+fn scene_title(file: &File, dialogue: &Model) -> String {
+    let mut titles = vec![dialogue.display_name().unwrap_or_default()];
+    let mut parent = dialogue.parent();
+
+    // NOTE: Bounded in case of a malformed parent chain
+    for _ in 0..MAX_HIERARCHY_DEPTH {
+        let Some(model) = file.get_model(&parent) else {
+            break;
+        };
+
+        if let Model::FlowFragment { display_name, .. } = model {
+            titles.push(display_name.clone());
+        }
+
+        parent = model.parent();
+    }
+
+    titles.reverse();
+    titles.join(" / ")
+}
+
+impl VoiceScript {
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.speaker_name);
+
+        for scene in &self.scenes {
+            markdown.push_str(&format!(
+                "\n## {} (`{}`)\n\n",
+                scene.title, scene.dialogue.0
+            ));
+
+            for line in &scene.lines {
+                markdown.push_str(&format!("- `{}` ", line.id.0));
+
+                if !line.stage_directions.is_empty() {
+                    markdown.push_str(&format!("*({})* ", line.stage_directions));
+                }
+
+                markdown.push_str(&format!("{}\n", line.text));
+            }
+        }
+
+        markdown
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = "speaker,scene,dialogue,id,stage_directions,text\n".to_owned();
+
+        for scene in &self.scenes {
+            for line in &scene.lines {
+                let row = [
+                    self.speaker_name.as_str(),
+                    &scene.title,
+                    scene.dialogue.as_str(),
+                    line.id.as_str(),
+                    &line.stage_directions,
+                    &line.text,
+                ]
+                .map(escape_csv);
+
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+        }
+
+        csv
+    }
+}
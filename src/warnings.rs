@@ -0,0 +1,73 @@
+use std::cell::RefCell;
+
+use serde_json::Value;
+
+use crate::types::{File, Id};
+
+/// A decision the loader made on data it couldn't read as is, see `File::load_warnings`
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadWarning {
+    /// A boolean setting that was neither "True" nor "False", read as false
+    InvalidBool(String),
+    /// An entry of the included nodes setting that isn't known, read as `NodeType::Unknown`
+    UnknownNodeType(String),
+    /// An output pin exported without its connections, read as not connected
+    MissingConnections { pin: Id },
+    /// A model of one of the typed kinds that didn't match its variant and became a `Model::Custom`
+    FellBackToCustom {
+        id: Id,
+        kind: String,
+        reason: String,
+    },
+}
+
+impl File {
+    /// Everything the loader had to guess or default while reading the export, e.g to fail builds on degraded data
+    pub fn load_warnings(&self) -> &[LoadWarning] {
+        &self.load_warnings
+    }
+}
+
+thread_local! {
+    static WARNINGS: RefCell<Option<Vec<LoadWarning>>> = const { RefCell::new(None) };
+}
+
+/// Runs `load` while collecting the warnings raised through `warn`
+pub(crate) fn collect_during<T>(load: impl FnOnce() -> T) -> (T, Vec<LoadWarning>) {
+    WARNINGS.set(Some(vec![]));
+    let loaded = load();
+    let warnings = WARNINGS.take().unwrap_or_default();
+
+    (loaded, warnings)
+}
+
+/// Records a warning, outside of `collect_during` (e.g deserializing a `File` directly) it's dropped
+pub(crate) fn warn(warning: LoadWarning) {
+    WARNINGS.with_borrow_mut(|warnings| {
+        if let Some(warnings) = warnings {
+            warnings.push(warning);
+        }
+    });
+}
+
+/// Pins missing their connections are defaulted by serde without knowing which pin it was, so they're looked for up front.
+/// Input pins are left out, Articy only exports their connections for some kinds of nodes.
+pub(crate) fn warn_about_missing_connections(value: &Value) {
+    let pins = value
+        .get("packages")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|package| package.get("models")?.as_array())
+        .flatten()
+        .filter_map(|model| model.get("properties"))
+        .filter_map(|properties| properties.get("output_pins")?.as_array())
+        .flatten();
+
+    for pin in pins {
+        if pin.get("connections").is_none() {
+            let id = pin.get("id").and_then(Value::as_str).unwrap_or_default();
+            warn(LoadWarning::MissingConnections { pin: Id(id.into()) });
+        }
+    }
+}